@@ -0,0 +1,129 @@
+static FENCE: &str = "---";
+static TAGS_KEY: &str = "tags";
+
+/// A chapter's leading `---` fenced frontmatter block
+#[derive(Debug, PartialEq)]
+pub struct Frontmatter<'a> {
+    pub tags: Vec<&'a str>,
+    /// The rest of the chapter after the frontmatter block
+    pub content: &'a str,
+}
+
+impl<'a> Frontmatter<'a> {
+    /// Split the frontmatter off the front of our content, returns `None` if there isn't any.
+    ///
+    /// This is a deliberately small subset of YAML, we only understand the `tags` key as either a
+    /// flow list `tags: [rust, async]`, a block list of `- rust` entries or a single value.
+    pub fn parse(content: &'a str) -> Option<Frontmatter<'a>> {
+        let mut lines = content.split_inclusive('\n');
+        let mut offset = match lines.next() {
+            Some(line) if line.trim_end() == FENCE => line.len(),
+            _ => return None,
+        };
+
+        let mut block = vec![];
+        let mut closed = false;
+        for line in lines {
+            offset += line.len();
+            if line.trim_end() == FENCE {
+                closed = true;
+                break;
+            }
+            block.push(line.trim_end());
+        }
+
+        if !closed {
+            return None;
+        }
+
+        Some(Frontmatter {
+            tags: parse_tags(&block),
+            content: &content[offset..],
+        })
+    }
+}
+
+fn parse_tags<'a>(block: &[&'a str]) -> Vec<&'a str> {
+    let mut tags = vec![];
+
+    let mut lines = block.iter();
+    while let Some(line) = lines.next() {
+        let value = match line
+            .strip_prefix(TAGS_KEY)
+            .and_then(|rest| rest.trim_start().strip_prefix(':'))
+        {
+            Some(value) => value.trim(),
+            None => continue,
+        };
+
+        if value.is_empty() {
+            // a block list, our entries follow on their own lines
+            for entry in lines.by_ref() {
+                match entry.trim_start().strip_prefix('-') {
+                    Some(tag) => tags.push(tag),
+                    None => break,
+                }
+            }
+        } else if let Some(list) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            tags.extend(list.split(','));
+        } else {
+            tags.push(value);
+        }
+        break;
+    }
+
+    tags.into_iter()
+        .map(|tag| tag.trim().trim_matches(|c| c == '"' || c == '\''))
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn flow_list() {
+        let frontmatter = Frontmatter::parse(
+            r#"---
+title: Chapter
+tags: [rust, "async", ]
+---
+# Chapter"#,
+        );
+
+        assert_eq!(
+            Some(Frontmatter {
+                tags: vec!["rust", "async"],
+                content: "# Chapter",
+            }),
+            frontmatter
+        );
+    }
+
+    #[test]
+    fn block_list() {
+        let frontmatter = Frontmatter::parse(
+            r#"---
+tags:
+  - rust
+  - async
+title: Chapter
+---
+# Chapter"#,
+        )
+        .unwrap();
+
+        assert_eq!(vec!["rust", "async"], frontmatter.tags);
+        assert_eq!("# Chapter", frontmatter.content);
+    }
+
+    #[test]
+    fn no_frontmatter() {
+        assert_eq!(
+            None,
+            Frontmatter::parse("# Chapter\n---\ntags: [rust]\n---")
+        );
+        assert_eq!(None, Frontmatter::parse("---\ntags: [rust]\n"));
+    }
+}
@@ -0,0 +1,4 @@
+mod frontmatter;
+pub mod preprocessor;
+mod verify;
+//mod renderer;
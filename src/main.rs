@@ -1,10 +1,8 @@
-mod preprocessor;
-//mod renderer;
-
-use crate::preprocessor::{TagPreprocessor, PREPROCESSOR_NAME};
 use clap::{App, Arg, ArgMatches, SubCommand};
-use mdbook::errors::Error;
+use env_logger::Env;
+use mdbook::errors::{Error, ResultExt};
 use mdbook::preprocess::{CmdPreprocessor, Preprocessor};
+use mdbook_tag::preprocessor::{TagPreprocessor, PREPROCESSOR_NAME};
 use std::io;
 use std::process;
 
@@ -19,6 +17,9 @@ pub fn make_app() -> App<'static, 'static> {
 }
 
 fn main() {
+    // log to stderr at the Info level like mdbook does, stdout is reserved for our book
+    env_logger::from_env(Env::default().default_filter_or("info")).init();
+
     let matches = make_app().get_matches();
 
     let preprocessor = TagPreprocessor::new();
@@ -27,12 +28,23 @@ fn main() {
         handle_supports(&preprocessor, sub_args);
     } else if let Err(e) = handle_preprocessing(&preprocessor) {
         eprintln!("{}", e);
+        for cause in e.iter().skip(1) {
+            eprintln!("\tCaused By: {}", cause);
+        }
         process::exit(1);
     }
 }
 
 fn handle_preprocessing(pre: &dyn Preprocessor) -> Result<(), Error> {
-    let (ctx, book) = CmdPreprocessor::parse_input(io::stdin())?;
+    // our input format changes between mdbook versions, so point at the likely culprit
+    let (ctx, book) = CmdPreprocessor::parse_input(io::stdin()).chain_err(|| {
+        format!(
+            "The {} plugin was built against version {} of mdbook, check that it matches \
+             the version of mdbook being used",
+            pre.name(),
+            mdbook::MDBOOK_VERSION
+        )
+    })?;
 
     if ctx.mdbook_version != mdbook::MDBOOK_VERSION {
         // We should probably use the `semver` crate to check compatibility
@@ -54,7 +66,7 @@ fn handle_preprocessing(pre: &dyn Preprocessor) -> Result<(), Error> {
 
 fn handle_supports(pre: &dyn Preprocessor, sub_args: &ArgMatches) -> ! {
     let renderer = sub_args.value_of("renderer").expect("Required argument");
-    let supported = pre.supports_renderer(&renderer);
+    let supported = pre.supports_renderer(renderer);
 
     // Signal whether the renderer is supported by exiting with 1 or 0.
     if supported {
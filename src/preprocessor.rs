@@ -1,22 +1,144 @@
-use mdbook::book::{Book, Chapter};
+use crate::frontmatter::Frontmatter;
+use crate::verify::rendered_ids;
+pub use builder::TaggerBuilder;
+use glob::Pattern;
+use log::{debug, info, warn};
+use mdbook::book::{Book, Chapter, SectionNumber};
 use mdbook::errors::Error;
 use mdbook::errors::ErrorKind;
 use mdbook::errors::Result;
+use mdbook::errors::ResultExt;
 use mdbook::preprocess::{Preprocessor, PreprocessorContext};
-use mdbook::utils::fs::path_to_root;
-use mdbook::utils::new_cmark_parser;
+use mdbook::utils::fs::{create_file, path_to_root};
+use mdbook::utils::{id_from_content, new_cmark_parser};
 use mdbook::BookItem;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
 use pulldown_cmark as md;
 use pulldown_cmark_to_cmark::fmt::cmark;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fs;
 use std::io;
-use std::path::PathBuf;
+use std::io::{Read, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 use toml::value::Table;
 use toml::Value;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+mod builder;
 
 pub static PREPROCESSOR_NAME: &str = "tag";
 pub static TAG_STRING_PREFIX: &str = "tag:";
+pub static TAG_CLOUD_MARKER: &str = "tags:cloud";
+pub static TAG_BLOCK_INFO: &str = "tags";
+pub static SILENT_TAG_STRING_PREFIX: &str = "tag!:";
+pub static TOP_ANCHOR: &str = "top";
+pub static ALL_TAGS_FILENAME: &str = "all.md";
+pub static NO_TAGS_MESSAGE: &str = "No tags yet";
+/// The first line of the tags page we generate, so we can tell it apart from a chapter of the book
+pub static GENERATED_MARKER: &str = "<!-- mdbook-tag -->";
+
+/// Every key we read from our `[preprocessor.tag]` table, along with the keys mdbook reads itself
+static CONFIG_KEYS: &[&str] = &[
+    "command",
+    "renderers",
+    "before",
+    "after",
+    "namespaces",
+    "alias_spaces",
+    "always_generate",
+    "anchor_prefix",
+    "anchors",
+    "atom_output",
+    "back_to_top",
+    "base_url",
+    "case_sensitive",
+    "chapter_footer",
+    "chapter_layout",
+    "chapter_name_transform",
+    "collision_check",
+    "colors",
+    "dedupe",
+    "delimiter",
+    "definitions_file",
+    "descriptions",
+    "directory",
+    "display",
+    "display_names",
+    "draft_parent",
+    "dry_run",
+    "embed_metadata",
+    "exclude",
+    "external",
+    "featured",
+    "filename",
+    "group_by",
+    "heading_offset",
+    "ignore",
+    "ignore_inline",
+    "inline",
+    "json_output",
+    "jump_bar",
+    "layout",
+    "link_style",
+    "max_chapters_per_tag",
+    "min_count",
+    "mode",
+    "normalize",
+    "order",
+    "parent",
+    "position",
+    "preamble",
+    "prefix",
+    "primary",
+    "primary_auto",
+    "rare_inline",
+    "related",
+    "renderer_paths",
+    "report_untagged",
+    "scan_alt",
+    "section_template",
+    "separator",
+    "show_counts",
+    "silent_prefix",
+    "sitemap_output",
+    "snippet_length",
+    "snippets",
+    "sort",
+    "sort_chapters",
+    "split",
+    "split_size",
+    "strict_singletons",
+    "strict_syntax",
+    "strip_display_prefix",
+    "summary",
+    "synonyms",
+    "title",
+    "title_format",
+    "verbose",
+    "verify_links",
+    "warn_singletons",
+    "weights",
+];
+
+/// The keys our namespaces don't inherit from the top level, they need to be unique to each one
+static NAMESPACE_KEYS: &[&str] = &[
+    "atom_output",
+    "json_output",
+    "silent_prefix",
+    "sitemap_output",
+];
+
+/// The tables a `definitions_file` can set
+static DEFINITION_KEYS: &[&str] = &["colors", "descriptions", "synonyms"];
 
+#[derive(Default)]
 pub struct TagPreprocessor {}
 
 impl TagPreprocessor {
@@ -31,411 +153,8975 @@ impl Preprocessor for TagPreprocessor {
     }
 
     fn run(&self, ctx: &PreprocessorContext, mut book: Book) -> Result<Book> {
-        let tagger = Tagger::new(ctx.config.get_preprocessor(self.name()));
+        let config = with_definitions(&ctx.root, ctx.config.get_preprocessor(self.name()))?;
+        let config = config.map(|config| with_renderer_paths(&ctx.renderer, config));
 
-        let mut tag_results: Vec<Result<Vec<AliasedTag>>> = vec![];
+        // each namespace is independent, so they tag our book one after the other
+        for mut tagger in Tagger::namespaces(config.as_ref())? {
+            tagger.src = ctx.config.book.src.clone();
+            tagger.book_title = ctx.config.book.title.clone();
+            tagger.authors = ctx.config.book.authors.clone();
+            // our badges are html, other renderers would show them as text
+            if ctx.renderer != "html" {
+                tagger.colors.clear();
+            }
+            book = tagger.tag_book(&ctx.root, book)?;
+        }
+
+        Ok(book)
+    }
+
+    fn supports_renderer(&self, _renderer: &str) -> bool {
+        // since we're just outputting markdown, this should support any renderer
+        true
+    }
+}
+
+/// The unique anchor id of each alias on our tags page
+pub type TagAnchors = HashMap<String, String>;
+
+/// Finds the tags in our chapters and builds our tags page from them
+#[derive(Clone)]
+pub struct Tagger {
+    output_filename: String,
+    directory: Option<String>,
+    delimiter: String,
+    title: String,
+    preamble: Option<String>,
+    prefix: String,
+    silent_prefix: String,
+    case_sensitive: bool,
+    normalize: Normalize,
+    alias_spaces: AliasSpaces,
+    display: Display,
+    title_format: String,
+    anchor_prefix: String,
+    link_style: LinkStyle,
+    inline: Inline,
+    mode: Mode,
+    layout: Layout,
+    chapter_layout: ChapterLayout,
+    show_counts: bool,
+    back_to_top: bool,
+    jump_bar: bool,
+    related: bool,
+    heading_offset: usize,
+    section_template: Option<String>,
+    group_by: GroupBy,
+    sort: Sort,
+    sort_chapters: ChapterSort,
+    chapter_name_transform: ChapterNameTransform,
+    order: Vec<String>,
+    featured: Vec<String>,
+    max_chapters_per_tag: Option<usize>,
+    split: Split,
+    split_size: usize,
+    position: Position,
+    separator: bool,
+    parent: Option<String>,
+    always_generate: bool,
+    dedupe: bool,
+    min_count: usize,
+    rare_inline: RareInline,
+    warn_singletons: bool,
+    strict_singletons: bool,
+    strict_syntax: bool,
+    exclude: Vec<Pattern>,
+    draft_parent: bool,
+    json_output: Option<PathBuf>,
+    atom_output: Option<PathBuf>,
+    sitemap_output: Option<PathBuf>,
+    base_url: Option<String>,
+    verify_links: bool,
+    collision_check: bool,
+    summary: bool,
+    report_untagged: bool,
+    verbose: bool,
+    dry_run: bool,
+    scan_alt: bool,
+    chapter_footer: bool,
+    embed_metadata: bool,
+    snippets: bool,
+    snippet_length: usize,
+    descriptions: HashMap<String, String>,
+    display_names: HashMap<String, String>,
+    strip_display_prefix: Vec<String>,
+    external: HashMap<String, String>,
+    weights: HashMap<String, usize>,
+    colors: HashMap<String, String>,
+    custom_anchors: TagAnchors,
+    primary: HashMap<String, PathBuf>,
+    primary_auto: bool,
+    synonyms: HashMap<String, String>,
+    ignore: HashSet<String>,
+    ignore_inline: IgnoreInline,
+    /// The page path of every namespace in our book, and whether it has pages nested under it
+    namespace_pages: Vec<(String, bool)>,
+    /// The directory of our chapters relative to the root of our book, from `book.toml`
+    src: PathBuf,
+    /// The title and authors of our book from `book.toml`, they tell our Atom feed apart from the
+    /// feeds of other books
+    book_title: Option<String>,
+    authors: Vec<String>,
+    /// When each of our chapters was last modified, only read for `sort = "recent"`
+    modified: HashMap<PathBuf, SystemTime>,
+    /// The ranges of letters of our split pages, found once from every tag in our book
+    letter_ranges: Option<Vec<Vec<Option<char>>>>,
+}
+
+impl Tagger {
+    /// Our `Tagger` from the `[preprocessor.tag]` table of our `book.toml`, or the defaults
+    pub fn new(config: Option<&Table>) -> Result<Tagger> {
+        match config {
+            Some(config) => Tagger::from_config(config),
+            None => TaggerBuilder::new().build(),
+        }
+    }
+
+    /// Our `Tagger` from a `[preprocessor.tag]` table, keys we don't know about and values we
+    /// can't use are rejected. Our `definitions_file` isn't read here,
+    /// see `with_definitions`.
+    pub fn from_config(config: &Table) -> Result<Tagger> {
+        validate_keys(config)?;
+        let config = Some(config);
+        let mut builder = TaggerBuilder::new();
+
+        if let Some(filename) = config_str(config, "filename")? {
+            builder.filename(filename);
+        }
+        if let Some(directory) = config_str(config, "directory")? {
+            builder.directory(directory);
+        }
+        if let Some(delimiter) = config_str(config, "delimiter")? {
+            builder.delimiter(delimiter);
+        }
+        if let Some(title) = config_str(config, "title")? {
+            builder.title(title);
+        }
+        if let Some(preamble) = config_str(config, "preamble")? {
+            builder.preamble(preamble);
+        }
+        if let Some(prefix) = config_str(config, "prefix")?.filter(|p| !p.is_empty()) {
+            builder.prefix(prefix);
+        }
+        if let Some(prefix) = config_str(config, "silent_prefix")?.filter(|p| !p.is_empty()) {
+            builder.silent_prefix(prefix);
+        }
+        if let Some(case_sensitive) = config_bool(config, "case_sensitive")? {
+            builder.case_sensitive(case_sensitive);
+        }
+        if let Some(normalize) =
+            config_choice(config, "normalize", Normalize::VALUES, Normalize::from_str)?
+        {
+            builder.normalize(normalize);
+        }
+        if let Some(alias_spaces) = config_choice(
+            config,
+            "alias_spaces",
+            AliasSpaces::VALUES,
+            AliasSpaces::from_str,
+        )? {
+            builder.alias_spaces(alias_spaces);
+        }
+        if let Some(display) = config_choice(config, "display", Display::VALUES, Display::from_str)?
+        {
+            builder.display(display);
+        }
+        if let Some(title_format) = config_str(config, "title_format")? {
+            builder.title_format(title_format);
+        }
+        if let Some(anchor_prefix) = config_str(config, "anchor_prefix")? {
+            builder.anchor_prefix(anchor_prefix);
+        }
+        if let Some(link_style) =
+            config_choice(config, "link_style", LinkStyle::VALUES, LinkStyle::from_str)?
+        {
+            builder.link_style(link_style);
+        }
+        if let Some(inline) = config_choice(config, "inline", Inline::VALUES, Inline::from_str)? {
+            builder.inline(inline);
+        }
+        if let Some(mode) = config_choice(config, "mode", Mode::VALUES, Mode::from_str)? {
+            builder.mode(mode);
+        }
+        if let Some(layout) = config_choice(config, "layout", Layout::VALUES, Layout::from_str)? {
+            builder.layout(layout);
+        }
+        if let Some(chapter_layout) = config_choice(
+            config,
+            "chapter_layout",
+            ChapterLayout::VALUES,
+            ChapterLayout::from_str,
+        )? {
+            builder.chapter_layout(chapter_layout);
+        }
+        if let Some(show_counts) = config_bool(config, "show_counts")? {
+            builder.show_counts(show_counts);
+        }
+        if let Some(back_to_top) = config_bool(config, "back_to_top")? {
+            builder.back_to_top(back_to_top);
+        }
+        if let Some(jump_bar) = config_bool(config, "jump_bar")? {
+            builder.jump_bar(jump_bar);
+        }
+        if let Some(related) = config_bool(config, "related")? {
+            builder.related(related);
+        }
+        if let Some(template) = config_str(config, "section_template")? {
+            builder.section_template(template);
+        }
+        if let Some(offset) = config_count(config, "heading_offset", 0)? {
+            builder.heading_offset(offset);
+        }
+        if let Some(group_by) =
+            config_choice(config, "group_by", GroupBy::VALUES, GroupBy::from_str)?
+        {
+            builder.group_by(group_by);
+        }
+        if let Some(sort) = config_choice(config, "sort", Sort::VALUES, Sort::from_str)? {
+            builder.sort(sort);
+        }
+        if let Some(sort_chapters) = config_choice(
+            config,
+            "sort_chapters",
+            ChapterSort::VALUES,
+            ChapterSort::from_str,
+        )? {
+            builder.sort_chapters(sort_chapters);
+        }
+        if let Some(transform) = config_choice(
+            config,
+            "chapter_name_transform",
+            ChapterNameTransform::VALUES,
+            ChapterNameTransform::from_str,
+        )? {
+            builder.chapter_name_transform(transform);
+        }
+        builder.order(config_str_list(config, "order")?);
+        builder.featured(config_str_list(config, "featured")?);
+        if let Some(max) = config_count(config, "max_chapters_per_tag", 1)? {
+            builder.max_chapters_per_tag(max);
+        }
+        if let Some(split) = config_choice(config, "split", Split::VALUES, Split::from_str)? {
+            builder.split(split);
+        }
+        if let Some(size) = config_count(config, "split_size", 1)? {
+            builder.split_size(size);
+        }
+        if let Some(position) =
+            config_choice(config, "position", Position::VALUES, Position::from_str)?
+        {
+            builder.position(position);
+        }
+        if let Some(separator) = config_bool(config, "separator")? {
+            builder.separator(separator);
+        }
+        if let Some(parent) = config_str(config, "parent")? {
+            builder.parent(parent);
+        }
+        if let Some(always_generate) = config_bool(config, "always_generate")? {
+            builder.always_generate(always_generate);
+        }
+        if let Some(dedupe) = config_bool(config, "dedupe")? {
+            builder.dedupe(dedupe);
+        }
+        if let Some(min_count) = config_count(config, "min_count", 1)? {
+            builder.min_count(min_count);
+        }
+        if let Some(rare_inline) = config_choice(
+            config,
+            "rare_inline",
+            RareInline::VALUES,
+            RareInline::from_str,
+        )? {
+            builder.rare_inline(rare_inline);
+        }
+        if let Some(warn_singletons) = config_bool(config, "warn_singletons")? {
+            builder.warn_singletons(warn_singletons);
+        }
+        if let Some(strict_singletons) = config_bool(config, "strict_singletons")? {
+            builder.strict_singletons(strict_singletons);
+        }
+        if let Some(strict_syntax) = config_bool(config, "strict_syntax")? {
+            builder.strict_syntax(strict_syntax);
+        }
+        builder.exclude(config_str_list(config, "exclude")?);
+        if let Some(draft_parent) = config_bool(config, "draft_parent")? {
+            builder.draft_parent(draft_parent);
+        }
+        if let Some(json_output) = config_str(config, "json_output")? {
+            builder.json_output(json_output);
+        }
+        if let Some(atom_output) = config_str(config, "atom_output")? {
+            builder.atom_output(atom_output);
+        }
+        if let Some(sitemap_output) = config_str(config, "sitemap_output")? {
+            builder.sitemap_output(sitemap_output);
+        }
+        if let Some(base_url) = config_str(config, "base_url")? {
+            builder.base_url(base_url);
+        }
+        if let Some(verify_links) = config_bool(config, "verify_links")? {
+            builder.verify_links(verify_links);
+        }
+        if let Some(collision_check) = config_bool(config, "collision_check")? {
+            builder.collision_check(collision_check);
+        }
+        if let Some(summary) = config_bool(config, "summary")? {
+            builder.summary(summary);
+        }
+        if let Some(report_untagged) = config_bool(config, "report_untagged")? {
+            builder.report_untagged(report_untagged);
+        }
+        if let Some(verbose) = config_bool(config, "verbose")? {
+            builder.verbose(verbose);
+        }
+        if let Some(dry_run) = config_bool(config, "dry_run")? {
+            builder.dry_run(dry_run);
+        }
+        if let Some(scan_alt) = config_bool(config, "scan_alt")? {
+            builder.scan_alt(scan_alt);
+        }
+        if let Some(chapter_footer) = config_bool(config, "chapter_footer")? {
+            builder.chapter_footer(chapter_footer);
+        }
+        if let Some(embed_metadata) = config_bool(config, "embed_metadata")? {
+            builder.embed_metadata(embed_metadata);
+        }
+        if let Some(snippets) = config_bool(config, "snippets")? {
+            builder.snippets(snippets);
+        }
+        if let Some(length) = config_count(config, "snippet_length", 1)? {
+            builder.snippet_length(length);
+        }
+        for (alias, description) in config_str_table(config, "descriptions")? {
+            builder.description(alias, description);
+        }
+        for (alias, name) in config_str_table(config, "display_names")? {
+            builder.display_name(alias, name);
+        }
+        builder.strip_display_prefix(config_str_list(config, "strip_display_prefix")?);
+        for (alias, url) in config_str_table(config, "external")? {
+            builder.external(alias, url);
+        }
+        for (alias, weight) in config_table(config, "weights")?.into_iter().flatten() {
+            let weight = weight
+                .as_integer()
+                .and_then(|weight| count_at_least(weight, 0))
+                .ok_or_else(|| {
+                    invalid_value(&format!("weights.{}", alias), weight, &count_expected(0))
+                })?;
+            builder.weight(alias.as_str(), weight);
+        }
+        for (alias, color) in config_str_table(config, "colors")? {
+            builder.color(alias, color);
+        }
+        for (alias, anchor) in config_str_table(config, "anchors")? {
+            builder.anchor(alias, anchor);
+        }
+        for (alias, path) in config_str_table(config, "primary")? {
+            builder.primary(alias, path);
+        }
+        if let Some(primary_auto) = config_bool(config, "primary_auto")? {
+            builder.primary_auto(primary_auto);
+        }
+        for (synonym, canonical) in config_str_table(config, "synonyms")? {
+            builder.synonym(synonym, canonical);
+        }
+        builder.ignore(config_str_list(config, "ignore")?);
+        if let Some(ignore_inline) = config_choice(
+            config,
+            "ignore_inline",
+            IgnoreInline::VALUES,
+            IgnoreInline::from_str,
+        )? {
+            builder.ignore_inline(ignore_inline);
+        }
+
+        builder.build()
+    }
+
+    /// Build a `Tagger` for every namespace in our config. Each entry of the `namespaces` array
+    /// inherits the top level keys, without any namespaces our config is a single namespace.
+    pub fn namespaces(config: Option<&Table>) -> Result<Vec<Tagger>> {
+        let namespaces = match config.and_then(|t| t.get("namespaces")) {
+            Some(namespaces) => namespaces
+                .as_array()
+                .ok_or_else(|| Error::from("Expected namespaces to be an array of tables"))?,
+            None => return Ok(vec![Tagger::new(config)?]),
+        };
 
+        let mut defaults = config.cloned().unwrap_or_default();
+        defaults.remove("namespaces");
+        // every namespace writing to the same file or sharing our silent prefix would clobber the others
+        for key in NAMESPACE_KEYS {
+            defaults.remove(*key);
+        }
+
+        let mut taggers = namespaces
+            .iter()
+            .map(|namespace| {
+                let namespace = namespace
+                    .as_table()
+                    .ok_or_else(|| Error::from("Expected namespaces to be an array of tables"))?;
+
+                let mut config = defaults.clone();
+                config.extend(namespace.clone());
+
+                let mut tagger = Tagger::new(Some(&config))?;
+                if !namespace.contains_key("silent_prefix") {
+                    tagger.silent_prefix = silent_prefix(&tagger.prefix);
+                }
+
+                Ok(tagger)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // namespaces sharing a prefix or filename would fight over the same tags or page
+        let mut prefixes = HashSet::new();
+        let mut silent_prefixes = HashSet::new();
+        let mut filenames = HashSet::new();
+        let mut outputs = HashSet::new();
+        for tagger in taggers.iter() {
+            let paths = [
+                &tagger.json_output,
+                &tagger.atom_output,
+                &tagger.sitemap_output,
+            ];
+            for path in paths.iter().filter_map(|path| path.as_ref()) {
+                if !outputs.insert(path) {
+                    return Err(Error::from(format!(
+                        "Multiple tag namespaces write to \"{}\"",
+                        path.display()
+                    )));
+                }
+            }
+            if !prefixes.insert(&tagger.prefix) {
+                return Err(Error::from(format!(
+                    "Multiple tag namespaces use the prefix \"{}\"",
+                    tagger.prefix
+                )));
+            }
+            if !filenames.insert(tagger.page_path()) {
+                return Err(Error::from(format!(
+                    "Multiple tag namespaces use the filename \"{}\"",
+                    tagger.page_path()
+                )));
+            }
+            if !silent_prefixes.insert(&tagger.silent_prefix) {
+                return Err(Error::from(format!(
+                    "Multiple tag namespaces use the silent prefix \"{}\"",
+                    tagger.silent_prefix
+                )));
+            }
+        }
+
+        // each namespace tags the book in turn, so they need to skip the pages of the others
+        let pages = taggers
+            .iter()
+            .map(|tagger| (tagger.page_path(), tagger.has_sub_pages()))
+            .collect::<Vec<_>>();
+        for tagger in taggers.iter_mut() {
+            tagger.namespace_pages = pages.clone();
+        }
+
+        Ok(taggers)
+    }
+
+    /// Link all of the tags in our book and add our tags page, `root` is the root of our book
+    pub fn tag_book(&self, root: &Path, mut book: Book) -> Result<Book> {
+        // a dry run hands back the book exactly as we were given it
+        let original = if self.dry_run {
+            Some(book.clone())
+        } else {
+            None
+        };
+
+        // `mdbook serve` may hand us a book we've already tagged
+        self.remove_tags_page(&mut book);
+        self.check_collisions(&book)?;
+
+        let mut tags = self.collect_tags(&book)?;
+        if self.summary {
+            info!(
+                "Found {} unique tags with {} occurrences",
+                tags.len(),
+                tags.values().map(Vec::len).sum::<usize>()
+            );
+        }
+        if self.report_untagged {
+            for path in self.untagged_chapters(&book)? {
+                info!("Chapter {} has no tags", path.display());
+            }
+        }
+        self.check_singletons(&tags)?;
+
+        // rare tags are left off of our tags page
+        let mut rare = HashSet::new();
+        tags.retain(|alias, occurrences| {
+            let keep = occurrences.len() >= self.min_count;
+            if !keep {
+                rare.insert(alias.clone());
+            }
+            keep
+        });
+        if let Some(original) = original {
+            self.log_plan(&tags);
+            return Ok(original);
+        }
+        let anchors = self.tag_anchors(&tags);
+        // every link to one of our split pages needs the same ranges, so they're only found once
+        let ranged;
+        let tagger = if self.split == Split::AlphaRanges {
+            ranged = Tagger {
+                letter_ranges: Some(self.alpha_ranges(anchors.keys())),
+                ..self.clone()
+            };
+            &ranged
+        } else {
+            self
+        };
+
+        let mut tag_results: Vec<Result<Vec<AliasedTag>>> = vec![];
         book.for_each_mut(|item: &mut BookItem| {
             // only continue editing the book if we don't have any errors
             if !tag_results.iter().any(Result::is_err) {
                 if let BookItem::Chapter(ref mut chapter) = item {
-                    tag_results.push(tagger.process_chapter(chapter))
+                    let context = chapter_context(chapter);
+                    tag_results.push(
+                        tagger
+                            .link_chapter_except(chapter, &anchors, &rare)
+                            .chain_err(|| context),
+                    )
+                }
+            }
+        });
+        tag_results.into_iter().collect::<Result<Vec<_>>>()?;
+
+        // our clouds need every tag, so they're expanded once all of our chapters are processed
+        let mut cloud_results: Vec<Result<()>> = vec![];
+        book.for_each_mut(|item: &mut BookItem| {
+            if !cloud_results.iter().any(Result::is_err) {
+                if let BookItem::Chapter(ref mut chapter) = item {
+                    cloud_results.push(tagger.expand_tag_clouds(chapter, &tags))
                 }
             }
         });
+        cloud_results.into_iter().collect::<Result<Vec<_>>>()?;
 
-        let tags = tag_results
-            .into_iter()
-            .collect::<Result<Vec<_>>>()
-            .map(|raw_tags| {
-                let mut tags_map: HashMap<String, Vec<Tag>> = HashMap::new();
-
-                // collect all of our tags
-                for tag in raw_tags.into_iter().flatten() {
-                    match tags_map.get_mut(&tag.alias) {
-                        Some(existing_tags) => existing_tags.push(tag.tag),
-                        None => {
-                            tags_map.insert(tag.alias, vec![tag.tag]);
+        if let Some(json_output) = &self.json_output {
+            // the renderers clean out the build directory, so this lives relative to our book
+            let file = create_file(&root.join(json_output))?;
+            tagger.write_json(&tags, file)?;
+        }
+        if let Some(atom_output) = &self.atom_output {
+            let file = create_file(&root.join(atom_output))?;
+            tagger.write_atom(&tags, &read_modified(&root.join(&self.src), &tags), file)?;
+        }
+        if let Some(sitemap_output) = &self.sitemap_output {
+            let file = create_file(&root.join(sitemap_output))?;
+            tagger.write_sitemap(&tags, &anchors, file)?;
+        }
+
+        if !tags.is_empty() || self.always_generate {
+            let tag_page = if self.sort == Sort::Recent {
+                Tagger {
+                    modified: read_modified(&root.join(&self.src), &tags),
+                    ..tagger.clone()
+                }
+                .build_tags_page(tags.clone())?
+            } else {
+                tagger.build_tags_page(tags.clone())?
+            };
+
+            if self.verify_links {
+                tagger.check_links(&book, &tags, &anchors, &tag_page)?;
+            }
+
+            tagger.insert_tags_page(&mut book, tag_page);
+        }
+
+        Ok(book)
+    }
+
+    /// Log the tags page we would add for a dry run, along with every tag and its occurrences
+    fn log_plan(&self, tags: &HashMap<String, Vec<Tag>>) {
+        if tags.is_empty() && !self.always_generate {
+            info!("Dry run: no tags found, no tags page would be added");
+            return;
+        }
+
+        info!(
+            "Dry run: would add the tags page {} with {} tags",
+            self.page_path(),
+            tags.len()
+        );
+        let mut counts = tags
+            .iter()
+            .map(|(alias, occurrences)| (alias, occurrences.len()))
+            .collect::<Vec<_>>();
+        counts.sort_by_cached_key(|(alias, _)| collation_key(alias));
+        for (alias, count) in counts {
+            info!("  {}: {}", alias, count);
+        }
+    }
+
+    /// Scan every chapter of our book for tags without modifying it
+    pub fn collect_tags(&self, book: &Book) -> Result<HashMap<String, Vec<Tag>>> {
+        let mut raw_tags = vec![];
+        for item in book.iter() {
+            if let BookItem::Chapter(chapter) = item {
+                if let Some((_, tags)) = self
+                    .scan_chapter(chapter, &TagAnchors::new(), &HashSet::new())
+                    .chain_err(|| chapter_context(chapter))?
+                {
+                    raw_tags.extend(tags);
+                }
+            }
+        }
+        if self.draft_parent {
+            self.collect_draft_tags(&book.sections, None, &mut raw_tags)?;
+        }
+
+        Ok(self.build_tags_map(raw_tags))
+    }
+
+    /// Collect the tags of our draft chapters as if they were in their nearest ancestor that has
+    /// a path, drafts don't have a page so these tags link to the top of that ancestor
+    fn collect_draft_tags<'a>(
+        &self,
+        items: &'a [BookItem],
+        ancestor: Option<&'a Path>,
+        raw_tags: &mut Vec<AliasedTag>,
+    ) -> Result<()> {
+        for item in items {
+            if let BookItem::Chapter(chapter) = item {
+                let ancestor = if is_draft(&chapter.path) {
+                    if let Some(path) = ancestor {
+                        let linked = Chapter {
+                            path: path.to_path_buf(),
+                            ..chapter.clone()
+                        };
+                        if let Some((_, tags)) =
+                            self.scan_chapter(&linked, &TagAnchors::new(), &HashSet::new())?
+                        {
+                            raw_tags.extend(tags.into_iter().map(|mut aliased| {
+                                aliased.tag.anchor = None;
+                                aliased
+                            }));
                         }
                     }
+                    ancestor
+                } else {
+                    Some(chapter.path.as_path())
+                };
+
+                self.collect_draft_tags(&chapter.sub_items, ancestor, raw_tags)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replace the tags in our chapter with links to our tags page, without knowing about the
+    /// rest of our book so each tag links to its plain slug
+    pub fn process_chapter(&self, chapter: &mut Chapter) -> Result<Vec<AliasedTag>> {
+        self.link_chapter(chapter, &self.custom_anchors)
+    }
+
+    /// The paths of every chapter we scanned that doesn't have any tags, sorted by path
+    pub fn untagged_chapters(&self, book: &Book) -> Result<Vec<PathBuf>> {
+        let mut untagged = vec![];
+        for item in book.iter() {
+            if let BookItem::Chapter(chapter) = item {
+                if let Some((_, tags)) =
+                    self.scan_chapter(chapter, &TagAnchors::new(), &HashSet::new())?
+                {
+                    if tags.is_empty() {
+                        untagged.push(chapter.path.clone());
+                    }
                 }
+            }
+        }
+        untagged.sort();
 
-                tags_map
-            })?;
+        Ok(untagged)
+    }
 
-        if !tags.is_empty() {
-            let tag_page = tagger.build_tags_page(tags)?;
+    /// Replace the tags in some markdown with links to our tags page, as if it were the content of
+    /// a chapter at `path` relative to the book source. The chapter is named after its file.
+    pub fn transform_content(
+        &self,
+        content: &str,
+        path: &Path,
+    ) -> Result<(String, Vec<AliasedTag>)> {
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let chapter = Chapter::new(&name, content.into(), path, vec![]);
 
-            book.push_item(BookItem::Separator);
-            book.push_item(tag_page);
+        match self.scan_chapter(&chapter, &self.custom_anchors, &HashSet::new())? {
+            Some(transformed) => Ok(transformed),
+            None => Ok((content.into(), vec![])),
         }
+    }
 
-        Ok(book)
+    /// Replace the tags in our chapter with links to their `anchors` from `tag_anchors`
+    pub fn link_chapter(
+        &self,
+        chapter: &mut Chapter,
+        anchors: &TagAnchors,
+    ) -> Result<Vec<AliasedTag>> {
+        self.link_chapter_except(chapter, anchors, &HashSet::new())
     }
 
-    fn supports_renderer(&self, _renderer: &str) -> bool {
-        // since we're just outputting markdown, this should support any renderer
-        true
+    /// Replace the tags in our chapter with links, leaving the `unlisted` tags that don't appear
+    /// on our tags page as configured by `rare_inline`
+    fn link_chapter_except(
+        &self,
+        chapter: &mut Chapter,
+        anchors: &TagAnchors,
+        unlisted: &HashSet<String>,
+    ) -> Result<Vec<AliasedTag>> {
+        match self.scan_chapter(chapter, anchors, unlisted)? {
+            Some((content, tags)) => {
+                chapter.content = content;
+                if self.verbose {
+                    info!(
+                        "Processed chapter {}: found {} tags [{}]",
+                        chapter.path.display(),
+                        tags.len(),
+                        distinct_aliases(&tags).join(", ")
+                    );
+                }
+
+                Ok(tags)
+            }
+            None => Ok(vec![]),
+        }
     }
-}
 
-struct Tagger {
-    output_filename: String,
-}
+    /// Find all the tags in our chapter, along with what its content should be once they're
+    /// replaced with links. Returns `None` if this chapter shouldn't be tagged.
+    ///
+    /// The pages we generate are never scanned, so any tags in our preamble, descriptions or tag
+    /// clouds are shown exactly as they're written.
+    fn scan_chapter(
+        &self,
+        chapter: &Chapter,
+        anchors: &TagAnchors,
+        unlisted: &HashSet<String>,
+    ) -> Result<Option<(String, Vec<AliasedTag>)>> {
+        // draft chapters don't have anywhere for us to link to
+        if is_draft(&chapter.path)
+            || self.is_excluded(&chapter.path)
+            || self.is_generated_page(chapter)
+        {
+            return Ok(None);
+        }
+
+        let mut tags = vec![];
+        // the definitions of our reference links, in the order they're first used
+        let mut references = vec![];
+
+        // tags from our frontmatter apply to the whole chapter so they don't get an anchor
+        let content = match Frontmatter::parse(&chapter.content) {
+            Some(frontmatter) => {
+                for alias in frontmatter.tags {
+                    let key = self.normalize_alias(alias);
+                    if key.is_empty() || self.ignore.contains(&key) {
+                        continue;
+                    }
+
+                    tags.push(AliasedTag::new(
+                        key,
+                        chapter.name.clone(),
+                        chapter.path.clone(),
+                        chapter.parent_names.clone(),
+                        None,
+                    ));
+                }
+
+                frontmatter.content
+            }
+            None => &chapter.content,
+        };
+
+        // we only replace our tag spans so the rest of the chapter is left exactly as it was written
+        let mut replacements = vec![];
+        // a ```tags code block and its lines, each line is a single tag
+        let mut tag_block: Option<(Range<usize>, String)> = None;
+        // the text following our latest tags, along with where those tags start
+        let mut snippet: Option<(usize, String)> = None;
+        // how many images we're in, their alt text can't hold our links
+        let mut image_depth = 0;
+        for (event, range) in new_cmark_parser(content).into_offset_iter() {
+            let code = match event {
+                md::Event::Start(md::Tag::Image(..)) => {
+                    image_depth += 1;
+                    continue;
+                }
+                md::Event::End(md::Tag::Image(..)) => {
+                    image_depth -= 1;
+                    continue;
+                }
+                // the tags in our alt text are left as they're written, `scan_alt` only collects them
+                md::Event::Code(ref alt) if image_depth > 0 => {
+                    if self.scan_alt {
+                        self.collect_alt_tags(alt.trim(), chapter, &mut tags);
+                    }
+                    continue;
+                }
+                md::Event::Text(ref alt) if image_depth > 0 => {
+                    if self.scan_alt {
+                        for word in alt.split_whitespace() {
+                            self.collect_alt_tags(word, chapter, &mut tags);
+                        }
+                    }
+                    continue;
+                }
+                md::Event::Start(md::Tag::CodeBlock(ref info)) if info.trim() == TAG_BLOCK_INFO => {
+                    tag_block = Some((range, String::new()));
+                    continue;
+                }
+                md::Event::Text(ref text) => {
+                    if let Some((_, lines)) = &mut tag_block {
+                        lines.push_str(text);
+                    } else if let Some((_, following)) = &mut snippet {
+                        following.push_str(text);
+                    }
+                    continue;
+                }
+                md::Event::SoftBreak | md::Event::HardBreak => {
+                    if let Some((_, following)) = &mut snippet {
+                        following.push(' ');
+                    }
+                    continue;
+                }
+                // snippets end with the block their tags are in
+                md::Event::End(md::Tag::Paragraph)
+                | md::Event::End(md::Tag::Item)
+                | md::Event::End(md::Tag::Header(_))
+                | md::Event::End(md::Tag::TableCell) => {
+                    self.attach_snippet(&mut tags, snippet.take());
+                    continue;
+                }
+                md::Event::End(md::Tag::CodeBlock(_)) => {
+                    if let Some((range, lines)) = tag_block.take() {
+                        let mut events = vec![];
+                        let aliases = lines
+                            .lines()
+                            .map(str::trim)
+                            .filter(|l| !self.normalize_case(l).is_empty());
+                        for alias in aliases {
+                            if let Some(alias_events) = self.alias_events(
+                                alias,
+                                chapter,
+                                anchors,
+                                unlisted,
+                                &mut tags,
+                                &mut references,
+                            ) {
+                                events.push(md::Event::Start(md::Tag::Item));
+                                events.extend(alias_events);
+                                events.push(md::Event::End(md::Tag::Item));
+                            }
+                        }
+
+                        let replacement = if events.is_empty() {
+                            String::new()
+                        } else {
+                            events.insert(0, md::Event::Start(md::Tag::List(None)));
+                            events.push(md::Event::End(md::Tag::List(None)));
+                            to_markdown(&events)?
+                        };
+                        replacements.push((range, replacement));
+                    }
+                    continue;
+                }
+                md::Event::Code(ref raw_code) => raw_code.trim(),
+                _ => continue,
+            };
+
+            // `\tag:` is an escaped literal, it's shown without the backslash and never collected
+            if let Some(escaped) = code.strip_prefix('\\') {
+                if escaped.starts_with(self.prefix.as_str())
+                    || escaped.starts_with(self.silent_prefix.as_str())
+                {
+                    if let Some((_, following)) = &mut snippet {
+                        following.push_str(escaped);
+                    }
+                    let literal = md::Event::Code(escaped.to_string().into());
+                    replacements.push((range, to_markdown(&[literal])?));
+                    continue;
+                }
+            }
+
+            // silent tags are recorded without leaving anything behind in our chapter
+            if let Some(silent) = code.strip_prefix(self.silent_prefix.as_str()) {
+                let aliases = self.split_aliases(silent);
+                // like an empty `tag:`, the span is left as it's written so the typo stays visible
+                if aliases.is_empty() {
+                    self.malformed_tag(chapter, code)?;
+                    continue;
+                }
+
+                self.attach_snippet(&mut tags, snippet.take());
+                let start = tags.len();
+                for alias in aliases {
+                    let key = self.normalize_alias(alias);
+                    if !self.ignore.contains(&key) {
+                        tags.push(AliasedTag::new(
+                            key,
+                            chapter.name.clone(),
+                            chapter.path.clone(),
+                            chapter.parent_names.clone(),
+                            None,
+                        ));
+                    }
+                }
+
+                replacements.push((range, String::new()));
+                if self.snippets {
+                    snippet = Some((start, String::new()));
+                }
+                continue;
+            }
+
+            let prefix = self.prefix.as_str();
+            let aliases = if code.find(prefix) == Some(0) && code.len() > prefix.len() {
+                self.split_aliases(&code[prefix.len()..])
+            } else {
+                vec![]
+            };
+            if aliases.is_empty() {
+                if code.starts_with(prefix) {
+                    self.malformed_tag(chapter, code)?;
+                }
+
+                // plain code is part of our snippet like any other text
+                if let Some((_, following)) = &mut snippet {
+                    following.push_str(code);
+                }
+                continue;
+            }
+
+            self.attach_snippet(&mut tags, snippet.take());
+            let start = tags.len();
+            let mut events = vec![];
+            for alias in aliases {
+                if let Some(alias_events) = self.alias_events(
+                    alias,
+                    chapter,
+                    anchors,
+                    unlisted,
+                    &mut tags,
+                    &mut references,
+                ) {
+                    // separate each of our tags with a space
+                    if !events.is_empty() {
+                        events.push(md::Event::Text(" ".into()));
+                    }
+                    events.extend(alias_events);
+                }
+            }
+
+            replacements.push((range, to_markdown(&events)?));
+            if self.snippets {
+                snippet = Some((start, String::new()));
+            }
+        }
+        self.attach_snippet(&mut tags, snippet.take());
+
+        if self.alias_spaces == AliasSpaces::Error {
+            if let Some(aliased) = tags
+                .iter()
+                .find(|aliased| aliased.alias.contains(char::is_whitespace))
+            {
+                return Err(Error::from(format!(
+                    "Found the tag \"{}\" with spaces in {}, use dashes instead or set \
+                     alias_spaces to \"keep\" or \"hyphenate\"",
+                    aliased.alias,
+                    chapter.path.display()
+                )));
+            }
+        }
+
+        let mut content = splice(content, replacements);
+        if !references.is_empty() {
+            if !content.ends_with('\n') {
+                content.push('\n');
+            }
+            content.push('\n');
+            for (label, href, title) in references {
+                content.push_str(&format!("[{}]: {} \"{}\"\n", label, href, title));
+            }
+        }
+
+        if self.chapter_footer && !tags.is_empty() {
+            if !content.ends_with('\n') {
+                content.push('\n');
+            }
+            content.push('\n');
+            content.push_str(&self.chapter_footer(chapter, &tags, anchors, unlisted)?);
+        }
+
+        if self.embed_metadata && !tags.is_empty() {
+            let aliases = distinct_aliases(&tags);
+            // an html block is left as is by mdbook, so a theme script can read it from the page
+            let comment =
+                md::Event::Html(format!("<!-- tags: {} -->\n", aliases.join(", ")).into());
+
+            if !content.ends_with('\n') {
+                content.push('\n');
+            }
+            content.push('\n');
+            content.push_str(&to_markdown(&[comment])?);
+        }
+
+        for aliased in tags.iter_mut() {
+            aliased.tag.number = chapter.number.clone();
+        }
+
+        Ok(Some((content, tags)))
+    }
+
+    /// Collect the tags in a span of image alt text, like silent tags they don't get an anchor
+    fn collect_alt_tags(&self, span: &str, chapter: &Chapter, tags: &mut Vec<AliasedTag>) {
+        let aliases = match span
+            .strip_prefix(self.prefix.as_str())
+            .or_else(|| span.strip_prefix(self.silent_prefix.as_str()))
+        {
+            Some(aliases) => self.split_aliases(aliases),
+            None => return,
+        };
+
+        for alias in aliases {
+            let key = self.normalize_alias(alias);
+            if !self.ignore.contains(&key) {
+                tags.push(AliasedTag::new(
+                    key,
+                    chapter.name.clone(),
+                    chapter.path.clone(),
+                    chapter.parent_names.clone(),
+                    None,
+                ));
+            }
+        }
+    }
+
+    /// A tag span without any aliases is most likely a typo, so we warn about it or fail with
+    /// `strict_syntax`
+    fn malformed_tag(&self, chapter: &Chapter, code: &str) -> Result<()> {
+        let message = format!(
+            "Found the tag `{}` without any aliases in {}",
+            code,
+            chapter.path.display()
+        );
+
+        if self.strict_syntax {
+            Err(Error::from(message))
+        } else {
+            warn!("{}", message);
+            Ok(())
+        }
+    }
+
+    /// Give the tags from `snippet`'s start onwards the text that followed them, truncated to our
+    /// `snippet_length`. Tags without any following text don't get a snippet.
+    fn attach_snippet(&self, tags: &mut [AliasedTag], snippet: Option<(usize, String)>) {
+        let (start, following) = match snippet {
+            Some(snippet) => snippet,
+            None => return,
+        };
+
+        let following = following.split_whitespace().collect::<Vec<_>>().join(" ");
+        if following.is_empty() {
+            return;
+        }
+
+        let text = if following.chars().count() > self.snippet_length {
+            let truncated = following
+                .chars()
+                .take(self.snippet_length)
+                .collect::<String>();
+            format!("{}…", truncated.trim_end())
+        } else {
+            following
+        };
+
+        for aliased in tags[start..].iter_mut() {
+            aliased.tag.snippet = Some(text.clone());
+        }
+    }
+
+    /// The linked tag replacing a single alias in our chapter, adding it to `tags`. Reference links
+    /// add their label, href and title to `references` the first time they're used. Returns `None`
+    /// if the alias should be removed from the chapter entirely.
+    fn alias_events(
+        &self,
+        alias: &str,
+        chapter: &Chapter,
+        anchors: &TagAnchors,
+        unlisted: &HashSet<String>,
+        tags: &mut Vec<AliasedTag>,
+        references: &mut Vec<(String, String, String)>,
+    ) -> Option<Vec<md::Event<'static>>> {
+        let key = self.normalize_alias(alias);
+        let name = self.display_name(&key, alias);
+        let text = match self.display {
+            Display::Hash => format!("#{}", name),
+            Display::Name => name.to_string(),
+            Display::Both => format!("{} #", name),
+        };
+
+        // ignored tags never make it to our tags page, so there's nothing to link to
+        if self.ignore.contains(&key) {
+            return match self.ignore_inline {
+                IgnoreInline::Text => Some(vec![md::Event::Code(text.into())]),
+                IgnoreInline::Remove => None,
+            };
+        }
+        // without inline links our tags are only collected for our tags page, like silent tags
+        if self.inline == Inline::None {
+            tags.push(AliasedTag::new(
+                key,
+                chapter.name.clone(),
+                chapter.path.clone(),
+                chapter.parent_names.clone(),
+                None,
+            ));
+            return None;
+        }
+        // rare tags aren't on our tags page, so nothing links back to them
+        let rare = unlisted.contains(&key);
+        if rare && (self.rare_inline == RareInline::Text || self.inline == Inline::Text) {
+            tags.push(AliasedTag::new(
+                key,
+                chapter.name.clone(),
+                chapter.path.clone(),
+                chapter.parent_names.clone(),
+                None,
+            ));
+            return Some(vec![md::Event::Code(text.into())]);
+        }
+
+        // anchors only need to be unique within this chapter, but our other namespaces may have
+        // already numbered theirs so ours carry on from them
+        let numbered = chapter.content.matches(r#"<a id="tag-"#).count();
+        let anchor = format!("tag-{}-{}", slugify(alias), numbered + tags.len() + 1);
+
+        let tag = AliasedTag::new(
+            key.as_str(),
+            chapter.name.clone(),
+            chapter.path.clone(),
+            chapter.parent_names.clone(),
+            Some(anchor.clone()),
+        );
+
+        tags.push(tag);
+
+        let anchor = md::Event::InlineHtml(format!(r#"<a id="{}"></a>"#, anchor).into());
+        // our tags page still links back here, there's just no link from the tag itself
+        if self.inline == Inline::Text {
+            let mut events = vec![anchor];
+            events.extend(self.badge(&key, vec![md::Event::Code(text.into())]));
+            return Some(events);
+        }
+
+        let href = if rare {
+            self.chapter_rare_href(chapter, &key)
+        } else {
+            self.chapter_tag_href(chapter, &key, anchors)
+        };
+        let title = self.title_format.replace("{alias}", alias);
+
+        match self.link_style {
+            LinkStyle::Inline => {
+                let link = md::Tag::Link(md::LinkType::Inline, href.into(), title.into());
+
+                let mut events = vec![anchor];
+                events.extend(self.badge(
+                    &key,
+                    vec![
+                        md::Event::Start(link.clone()),
+                        md::Event::Code(text.into()),
+                        md::Event::End(link),
+                    ],
+                ));
+                Some(events)
+            }
+            LinkStyle::Reference => {
+                // our markdown writer only knows about inline links, so we write the brackets
+                // rare tags don't have an anchor of their own, so they can't share a label with one
+                let label = if rare {
+                    format!("tag:rare:{}", slugify(&key))
+                } else {
+                    format!("tag:{}", anchor_id(&key, anchors))
+                };
+                if !references.iter().any(|(existing, _, _)| *existing == label) {
+                    references.push((label.clone(), href, title));
+                }
+
+                let mut events = vec![anchor];
+                events.extend(self.badge(
+                    &key,
+                    vec![
+                        md::Event::Text("[".into()),
+                        md::Event::Code(text.into()),
+                        md::Event::Text(format!("][{}]", label).into()),
+                    ],
+                ));
+                Some(events)
+            }
+        }
+    }
+
+    /// The link from our chapter to a tag
+    fn chapter_tag_href(&self, chapter: &Chapter, alias: &str, anchors: &TagAnchors) -> String {
+        // external tags link straight to their url, it doesn't depend on where our chapter is
+        match self.external.get(alias) {
+            Some(url) => url.clone(),
+            None => format!(
+                "{}{}",
+                path_to_root(link_path(&chapter.path)),
+                self.tag_href(alias, anchors)
+            ),
+        }
+    }
+
+    /// The link from our chapter to a tag that's too rare to be on our tags page, there's no
+    /// section for it so this is the top of our tags page
+    fn chapter_rare_href(&self, chapter: &Chapter, alias: &str) -> String {
+        match self.external.get(alias) {
+            Some(url) => url.clone(),
+            None => format!(
+                "{}{}",
+                path_to_root(link_path(&chapter.path)),
+                encode_href(&self.page_path())
+            ),
+        }
+    }
+
+    /// A `Tagged: a, b` paragraph linking to each of the distinct tags in our chapter
+    fn chapter_footer(
+        &self,
+        chapter: &Chapter,
+        tags: &[AliasedTag],
+        anchors: &TagAnchors,
+        unlisted: &HashSet<String>,
+    ) -> Result<String> {
+        let aliases = distinct_aliases(tags);
+
+        let mut events = vec![
+            md::Event::Start(md::Tag::Paragraph),
+            md::Event::Text("Tagged: ".into()),
+        ];
+        for (i, alias) in aliases.into_iter().enumerate() {
+            if i > 0 {
+                events.push(md::Event::Text(", ".into()));
+            }
+
+            let name = self.display_name(alias, alias).to_string();
+            let href = if !unlisted.contains(alias) {
+                self.chapter_tag_href(chapter, alias, anchors)
+            } else if self.rare_inline == RareInline::Link {
+                self.chapter_rare_href(chapter, alias)
+            } else {
+                events.push(md::Event::Text(name.into()));
+                continue;
+            };
+
+            let link = md::Tag::Link(
+                md::LinkType::Inline,
+                href.into(),
+                self.title_format.replace("{alias}", alias).into(),
+            );
+            events.push(md::Event::Start(link.clone()));
+            events.push(md::Event::Text(name.into()));
+            events.push(md::Event::End(link));
+        }
+        events.push(md::Event::End(md::Tag::Paragraph));
+
+        to_markdown(&events)
+    }
+
+    /// Group all of our tags by their alias
+    fn build_tags_map<I>(&self, raw_tags: I) -> HashMap<String, Vec<Tag>>
+    where
+        I: IntoIterator<Item = AliasedTag>,
+    {
+        let mut tags_map: HashMap<String, Vec<Tag>> = HashMap::new();
+
+        // collect all of our tags
+        for AliasedTag { alias, tag } in raw_tags {
+            let existing_tags = tags_map.entry(alias).or_default();
+
+            // our anchors are unique per occurrence, so only compare the chapter itself
+            let duplicate = self.dedupe
+                && existing_tags
+                    .iter()
+                    .any(|t| t.path == tag.path && t.chapter_name == tag.chapter_name);
+            if !duplicate {
+                existing_tags.push(tag);
+            }
+        }
+
+        tags_map
+    }
+
+    /// Whether this chapter is our tags page, or one of its per-tag pages
+    fn is_tags_page(&self, chapter: &Chapter) -> bool {
+        is_page(&chapter.path, &self.page_path(), self.has_sub_pages())
+    }
+
+    /// Whether this chapter is a page generated by us or any of our other namespaces
+    fn is_generated_page(&self, chapter: &Chapter) -> bool {
+        self.is_tags_page(chapter)
+            || self
+                .namespace_pages
+                .iter()
+                .any(|(page_path, sub_pages)| is_page(&chapter.path, page_path, *sub_pages))
+    }
+
+    /// Remove a tags page, and the separator we added along with it, left over from an earlier run
+    fn remove_tags_page(&self, book: &mut Book) {
+        fn remove(tagger: &Tagger, items: &mut Vec<BookItem>, top: bool) -> bool {
+            // only our own page, a chapter of the book at the same path is a collision
+            let position = items.iter().position(|item| match item {
+                BookItem::Chapter(chapter) => {
+                    tagger.is_tags_page(chapter) && chapter.content.starts_with(GENERATED_MARKER)
+                }
+                BookItem::Separator => false,
+            });
+
+            match position {
+                Some(index) => {
+                    items.remove(index);
+
+                    // our separator sits between the tags page and the rest of the book, we never add
+                    // one under a parent chapter so any there belongs to the book
+                    let separator = match tagger.position {
+                        _ if !top || !tagger.separator => None,
+                        Position::Start => Some(index),
+                        Position::End => index.checked_sub(1),
+                    };
+                    if let Some(separator) = separator {
+                        if let Some(BookItem::Separator) = items.get(separator) {
+                            items.remove(separator);
+                        }
+                    }
+
+                    true
+                }
+                None => items.iter_mut().any(|item| match item {
+                    BookItem::Chapter(chapter) => remove(tagger, &mut chapter.sub_items, false),
+                    BookItem::Separator => false,
+                }),
+            }
+        }
+
+        remove(self, &mut book.sections, true);
+    }
+
+    /// Make sure our tags page won't shadow one of the chapters in our book
+    fn check_collisions(&self, book: &Book) -> Result<()> {
+        for item in book.iter() {
+            if let BookItem::Chapter(chapter) = item {
+                if self.is_tags_page(chapter) {
+                    return Err(Error::from(format!(
+                        "The tags page {} collides with the chapter \"{}\" at {}, configure a \
+                         different filename or directory",
+                        self.page_path(),
+                        chapter.name,
+                        chapter.path.display()
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check our exclude patterns against a chapter path relative to the source root
+    fn is_excluded(&self, path: &Path) -> bool {
+        let path = path.strip_prefix(".").unwrap_or(path);
+
+        self.exclude
+            .iter()
+            .any(|pattern| pattern.matches_path(path))
+    }
+
+    /// Find any tags that are only used once, these are often typos of another tag
+    fn check_singletons(&self, tags_map: &HashMap<String, Vec<Tag>>) -> Result<()> {
+        if !self.warn_singletons && !self.strict_singletons {
+            return Ok(());
+        }
+
+        let singletons = singletons(tags_map);
+        if singletons.is_empty() {
+            Ok(())
+        } else if self.strict_singletons {
+            Err(Error::from(format!(
+                "Found tags that are only used once: {}",
+                singletons.join(", ")
+            )))
+        } else {
+            for singleton in singletons {
+                warn!("Tag {} is only used once", singleton);
+            }
+
+            Ok(())
+        }
+    }
+
+    /// Make sure every link we generated will land on an id once mdbook renders our book as HTML,
+    /// both the links from our chapters to `tags_page` and the links back to each tag
+    fn check_links(
+        &self,
+        book: &Book,
+        tags_map: &HashMap<String, Vec<Tag>>,
+        anchors: &TagAnchors,
+        tags_page: &Chapter,
+    ) -> Result<()> {
+        let mut page_ids = HashMap::new();
+        page_ids.insert(
+            strip_current_dir(&tags_page.path).to_path_buf(),
+            rendered_ids(&tags_page.content),
+        );
+        for item in tags_page.sub_items.iter() {
+            if let BookItem::Chapter(chapter) = item {
+                page_ids.insert(
+                    strip_current_dir(&chapter.path).to_path_buf(),
+                    rendered_ids(&chapter.content),
+                );
+            }
+        }
+
+        let mut chapter_ids = HashMap::new();
+        for item in book.iter() {
+            if let BookItem::Chapter(chapter) = item {
+                if !is_draft(&chapter.path) {
+                    chapter_ids.insert(
+                        strip_current_dir(&chapter.path).to_path_buf(),
+                        rendered_ids(&chapter.content),
+                    );
+                }
+            }
+        }
+
+        let resolves =
+            |ids: &HashMap<PathBuf, HashSet<String>>, path: &Path, id: Option<&str>| match (
+                ids.get(strip_current_dir(path)),
+                id,
+            ) {
+                (Some(ids), Some(id)) => ids.contains(id),
+                (Some(_), None) => true,
+                (None, _) => false,
+            };
+
+        let mut unresolved = vec![];
+        for (alias, tags) in tags_map.iter() {
+            let href = self.tag_href(alias, anchors);
+            let decoded = percent_decode_str(&href).decode_utf8_lossy();
+            let mut parts = decoded.splitn(2, '#');
+            let path = Path::new(parts.next().unwrap_or_default());
+            if !resolves(&page_ids, path, parts.next()) {
+                unresolved.push(format!("\"{}\" -> {}", alias, href));
+            }
+
+            for tag in tags.iter().filter(|tag| !is_draft(&tag.path)) {
+                if let Some(anchor) = &tag.anchor {
+                    if !resolves(&chapter_ids, &tag.path, Some(anchor)) {
+                        unresolved.push(format!(
+                            "\"{}\" -> {}#{}",
+                            alias,
+                            tag.path.display(),
+                            anchor
+                        ));
+                    }
+                }
+            }
+        }
+        unresolved.sort();
+
+        if unresolved.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::from(format!(
+                "Found tag links that won't resolve: {}",
+                unresolved.join(", ")
+            )))
+        }
+    }
+
+    /// Normalize an alias into the key we group our tags by
+    fn normalize_alias(&self, alias: &str) -> String {
+        let alias = self.normalize_case(alias);
+
+        match self.synonyms.get(&alias) {
+            Some(canonical) => canonical.clone(),
+            None => alias,
+        }
+    }
+
+    fn normalize_case(&self, alias: &str) -> String {
+        // the same text can be composed of different code points, `é` vs `e` + `\u{301}`
+        let mut alias = alias.nfc().collect::<String>();
+        if self.alias_spaces == AliasSpaces::Hyphenate {
+            alias = alias.split_whitespace().collect::<Vec<_>>().join("-");
+        }
+        match self.normalize {
+            Normalize::Lower if self.case_sensitive => alias,
+            Normalize::Lower => alias.to_lowercase(),
+            Normalize::Slug => slug_alias(&alias),
+        }
+    }
+
+    /// Replace any tag cloud markers in our chapter with a link to every tag, each link has a
+    /// `data-count` attribute of its weighted count so themes can size them by how often they're
+    /// used
+    fn expand_tag_clouds(
+        &self,
+        chapter: &mut Chapter,
+        tags_map: &HashMap<String, Vec<Tag>>,
+    ) -> Result<()> {
+        if !chapter.content.contains(TAG_CLOUD_MARKER)
+            || is_draft(&chapter.path)
+            || self.is_excluded(&chapter.path)
+        {
+            return Ok(());
+        }
+
+        let anchors = self.tag_anchors(tags_map);
+        let mut sorted_tags = tags_map.iter().collect::<Vec<_>>();
+        sorted_tags.sort_by_cached_key(|(alias, _)| collation_key(alias));
+
+        let root = path_to_root(link_path(&chapter.path));
+        let links = sorted_tags
+            .into_iter()
+            .map(|(alias, tags)| {
+                format!(
+                    r#"<a class="tag-cloud-item" href="{}{}" data-count="{}">{}</a>"#,
+                    root,
+                    escape_html(&self.tag_href(alias, &anchors)),
+                    self.weighted_count(alias, tags),
+                    escape_html(self.display_name(alias, alias))
+                )
+            })
+            .collect::<Vec<_>>();
+        let cloud = format!(r#"<span class="tag-cloud">{}</span>"#, links.join(" "));
+
+        let replacements = new_cmark_parser(&chapter.content)
+            .into_offset_iter()
+            .filter(|(event, _)| match event {
+                md::Event::Code(code) => code.trim() == TAG_CLOUD_MARKER,
+                _ => false,
+            })
+            .map(|(_, range)| (range, cloud.clone()))
+            .collect::<Vec<_>>();
+
+        chapter.content = splice(&chapter.content, replacements);
+
+        Ok(())
+    }
+
+    /// Split the contents of a tag span into its individual aliases, skipping any entries that are
+    /// empty once they're normalized, like `++` as a slug
+    fn split_aliases<'a>(&self, raw_aliases: &'a str) -> Vec<&'a str> {
+        let aliases: Vec<&str> = if self.delimiter.is_empty() {
+            vec![raw_aliases]
+        } else {
+            raw_aliases.split(self.delimiter.as_str()).collect()
+        };
+
+        aliases
+            .into_iter()
+            .map(str::trim)
+            .filter(|alias| !self.normalize_case(alias).is_empty())
+            .collect()
+    }
+
+    fn build_tags_page(&self, tags_map: HashMap<String, Vec<Tag>>) -> Result<Chapter> {
+        let anchors = self.tag_anchors(&tags_map);
+        self.check_anchor_collisions(&anchors)?;
+
+        self.build_page(tags_map, &anchors)
+    }
+
+    /// Write the markdown of our tags page, without any of the pages nested under it
+    pub fn write_tags_page<W: Write>(
+        &self,
+        tags_map: HashMap<String, Vec<Tag>>,
+        writer: &mut W,
+    ) -> Result<()> {
+        let anchors = self.tag_anchors(&tags_map);
+        self.check_anchor_collisions(&anchors)?;
+        self.write_page(tags_map, &anchors, writer)?;
+
+        Ok(())
+    }
+
+    /// With `collision_check`, make sure none of our tag anchors share an id with the title or the
+    /// letter group headers of our tags page, a link to either would land on the wrong one
+    fn check_anchor_collisions(&self, anchors: &TagAnchors) -> Result<()> {
+        if !self.collision_check {
+            return Ok(());
+        }
+
+        let mut headers = vec![(id_from_content(&self.title), "the title".to_string())];
+        if self.group_by == GroupBy::Letter {
+            let letters = anchors
+                .keys()
+                .filter_map(|alias| letter_group(alias))
+                .collect::<BTreeSet<_>>();
+            for letter in letters {
+                headers.push((
+                    id_from_content(&letter.to_string()),
+                    format!("the \"{}\" group header", letter),
+                ));
+            }
+        }
+
+        let mut collisions = vec![];
+        for (alias, _) in anchors.iter() {
+            let id = self.tag_anchor_id(alias, anchors);
+            if let Some((_, header)) = headers.iter().find(|(header_id, _)| *header_id == id) {
+                collisions.push(format!("\"{}\" with {}", alias, header));
+            }
+        }
+        collisions.sort();
+
+        if collisions.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::from(format!(
+                "Found tag anchors that collide with the headers of the tags page {}: {}",
+                self.page_path(),
+                collisions.join(", ")
+            )))
+        }
+    }
+
+    /// Build our tags page using the anchors of every tag in our book, our sub pages only have
+    /// some of our tags but their anchors still need to match our links
+    fn build_page(
+        &self,
+        tags_map: HashMap<String, Vec<Tag>>,
+        anchors: &TagAnchors,
+    ) -> Result<Chapter> {
+        let mut content = vec![];
+        let sub_items = self.write_page(tags_map, anchors, &mut content)?;
+
+        Ok(Chapter {
+            name: self.title.clone(),
+            content: String::from_utf8(content).expect("Our markdown is always utf8"),
+            number: None,
+            sub_items,
+            path: format!("./{}", self.page_path()).into(),
+            parent_names: vec![],
+        })
+    }
+
+    /// Write the markdown of our tags page, returning the pages nested under it
+    fn write_page<W: Write>(
+        &self,
+        tags_map: HashMap<String, Vec<Tag>>,
+        anchors: &TagAnchors,
+        writer: &mut W,
+    ) -> Result<Vec<BookItem>> {
+        // our tags page may live in a directory so our links to chapters need to climb out of it
+        let root = path_to_root(self.page_path());
+        let mut contents = vec![];
+        if self.back_to_top && self.mode == Mode::Single {
+            push_anchor(&mut contents, TOP_ANCHOR);
+        }
+        contents.push(md::Event::Start(md::Tag::Header(self.header_level(1))));
+        contents.push(md::Event::Text(self.title.clone().into()));
+        contents.push(md::Event::End(md::Tag::Header(self.header_level(1))));
+        if let Some(preamble) = &self.preamble {
+            contents.extend(new_cmark_parser(preamble));
+        }
+
+        // an empty book still gets a page when `always_generate` is set, so its url never breaks
+        if tags_map.is_empty() {
+            contents.push(md::Event::Start(md::Tag::Paragraph));
+            contents.push(md::Event::Text(NO_TAGS_MESSAGE.into()));
+            contents.push(md::Event::End(md::Tag::Paragraph));
+        }
+
+        let mut sorted_tags = tags_map.into_iter().collect::<Vec<_>>();
+        match self.sort {
+            // compare each level of our hierarchy so nested tags stay next to their parents
+            Sort::Alpha => sorted_tags.sort_by_cached_key(|(alias, _)| collation_key(alias)),
+            Sort::Count => sorted_tags.sort_by_cached_key(|(alias, tags)| {
+                (
+                    Reverse(self.weighted_count(alias, tags)),
+                    collation_key(alias),
+                )
+            }),
+            Sort::Recent => {
+                sorted_tags.sort_by_cached_key(|(alias, _)| collation_key(alias));
+                // tags without any readable timestamps keep their alphabetical order at the end
+                sorted_tags.sort_by_cached_key(|(_, tags)| Reverse(self.last_modified(tags)));
+            }
+        }
+        // pinned tags go first, our sort is stable so the rest keep their order
+        sorted_tags.sort_by_key(|(alias, _)| {
+            self.order
+                .iter()
+                .position(|pinned| pinned == alias)
+                .unwrap_or(usize::MAX)
+        });
+
+        let mut sub_items = vec![];
+        if !self.featured.is_empty() {
+            sorted_tags.sort_by_key(|(alias, _)| {
+                self.featured
+                    .iter()
+                    .position(|featured| featured == alias)
+                    .unwrap_or(usize::MAX)
+            });
+
+            // our single page only lists the featured tags, so every tag still needs a page to
+            // link to
+            if self.mode == Mode::Single {
+                let all_tags = self
+                    .all_tags_tagger()
+                    .build_page(sorted_tags.iter().cloned().collect(), anchors)?;
+                sub_items.push(BookItem::Chapter(Chapter {
+                    parent_names: vec![self.title.clone()],
+                    ..all_tags
+                }));
+                sorted_tags.retain(|(alias, _)| self.featured.contains(alias));
+            }
+        } else if self.mode == Mode::Single && self.split == Split::AlphaRanges {
+            // each range of letters gets a page of its own, our page only links to them
+            contents.push(md::Event::Start(md::Tag::List(None)));
+            for letters in self.letter_ranges(anchors).iter() {
+                let range_tagger = self.range_tagger(letters);
+                let range_tags = sorted_tags
+                    .iter()
+                    .filter(|(alias, _)| letters.contains(&letter_group(alias)))
+                    .cloned()
+                    .collect();
+                let range_page = range_tagger.build_page(range_tags, anchors)?;
+
+                let link = md::Tag::Link(
+                    md::LinkType::Inline,
+                    self.sub_page_href(&range_tagger.output_filename).into(),
+                    range_page.name.clone().into(),
+                );
+                contents.push(md::Event::Start(md::Tag::Item));
+                contents.push(md::Event::Start(link.clone()));
+                contents.push(md::Event::Text(range_page.name.clone().into()));
+                contents.push(md::Event::End(link));
+                contents.push(md::Event::End(md::Tag::Item));
+
+                sub_items.push(BookItem::Chapter(Chapter {
+                    parent_names: vec![self.title.clone()],
+                    ..range_page
+                }));
+            }
+            contents.push(md::Event::End(md::Tag::List(None)));
+
+            writer.write_all(to_markdown(&contents)?.as_bytes())?;
+            return Ok(sub_items);
+        }
+
+        if self.jump_bar && self.mode == Mode::Single {
+            self.push_jump_bar(&mut contents, &sorted_tags, anchors);
+        }
+
+        match self.mode {
+            Mode::Single if self.layout == Layout::Table => {
+                self.push_tags_table(&mut contents, sorted_tags, anchors, &root)?
+            }
+            Mode::Single => {
+                group_nested(&mut sorted_tags);
+                if self.group_by == GroupBy::Letter {
+                    // symbols go first, our sort is stable so each group stays alphabetical
+                    sorted_tags.sort_by_key(|(alias, _)| letter_group(alias));
+                }
+
+                let related = if self.related {
+                    related_tags(&sorted_tags)
+                } else {
+                    HashMap::new()
+                };
+
+                let mut current_group = None;
+                let mut open_parents: Vec<&str> = vec![];
+                let mut parent_anchors = HashSet::new();
+                for (alias, tags) in sorted_tags.iter() {
+                    if self.group_by == GroupBy::Letter {
+                        let group = Some(letter_group(alias));
+                        if group != current_group {
+                            let heading = match group {
+                                Some(Some(letter)) => letter.to_string(),
+                                _ => "#".to_string(),
+                            };
+
+                            contents.push(md::Event::Start(md::Tag::Header(self.header_level(1))));
+                            contents.push(md::Event::Text(heading.into()));
+                            contents.push(md::Event::End(md::Tag::Header(self.header_level(1))));
+                            current_group = group;
+                            open_parents.clear();
+                        }
+                    }
+
+                    // nested tags like `lang/rust` are listed under a header for each parent
+                    let segments = alias.split('/').collect::<Vec<_>>();
+                    let (name, parents) = segments.split_last().expect("split is never empty");
+                    let shared_parents = open_parents
+                        .iter()
+                        .zip(parents)
+                        .take_while(|(a, b)| a == b)
+                        .count();
+                    for depth in shared_parents..parents.len() {
+                        let parent_id = self.tag_anchor_id(&parents[..=depth].join("/"), anchors);
+                        if parent_anchors.insert(parent_id.clone()) {
+                            push_anchor(&mut contents, &parent_id);
+                        }
+                        let parent = parents[..=depth].join("/");
+                        let name = self.display_name(&parent, parents[depth]).to_string();
+                        push_header(
+                            &mut contents,
+                            self.header_level(depth + 2),
+                            self.badge(&parent, vec![md::Event::Code(name.into())]),
+                        );
+                    }
+                    open_parents = segments.clone();
+
+                    // mdbook's header ids aren't predictable, so link to our own anchor
+                    let level = self.header_level(parents.len() + 2);
+                    push_anchor(&mut contents, &self.tag_anchor_id(alias, anchors));
+                    match &self.section_template {
+                        Some(template) => {
+                            let section =
+                                self.fill_section_template(template, alias, tags, &root)?;
+                            contents.push(md::Event::Start(md::Tag::HtmlBlock));
+                            contents.push(md::Event::Html(section.into()));
+                            contents.push(md::Event::End(md::Tag::HtmlBlock));
+                        }
+                        None if self.layout == Layout::Deflist => {
+                            let definition = self.tag_definition(alias, name, tags, &root)?;
+                            contents.push(md::Event::Start(md::Tag::HtmlBlock));
+                            contents.push(md::Event::Html(definition.into()));
+                            contents.push(md::Event::End(md::Tag::HtmlBlock));
+                        }
+                        None => {
+                            contents.push(md::Event::Start(md::Tag::Header(level)));
+                            contents.extend(self.tag_name(alias, name));
+                            self.push_count(&mut contents, tags.len());
+                            contents.push(md::Event::End(md::Tag::Header(level)));
+                            if let Some(related) = related.get(alias) {
+                                self.push_related(&mut contents, related, anchors, &root);
+                            }
+
+                            if self.layout == Layout::Glossary {
+                                if let Some(description) = self.descriptions.get(alias) {
+                                    contents.extend(new_cmark_parser(description));
+                                }
+                            } else {
+                                self.push_tag_section(&mut contents, alias, tags.clone(), &root)?;
+                            }
+                        }
+                    }
+
+                    if self.back_to_top {
+                        let link = md::Tag::Link(
+                            md::LinkType::Inline,
+                            format!("#{}", TOP_ANCHOR).into(),
+                            "Back to top".into(),
+                        );
+
+                        contents.push(md::Event::Start(md::Tag::Paragraph));
+                        contents.push(md::Event::Start(link.clone()));
+                        contents.push(md::Event::Text("↑ Top".into()));
+                        contents.push(md::Event::End(link));
+                        contents.push(md::Event::End(md::Tag::Paragraph));
+                    }
+                }
+            }
+            Mode::PerTag => {
+                contents.push(md::Event::Start(md::Tag::List(None)));
+                for (alias, tags) in sorted_tags {
+                    let page_path = self.tag_page_path(&alias, anchors);
+                    // every tag gets a page, but only our featured tags are in the index
+                    if self.featured.is_empty() || self.featured.contains(&alias) {
+                        // our index shares its directory with the tag pages
+                        let index_link = match &self.directory {
+                            Some(directory) => page_path[directory.len() + 1..].to_string(),
+                            None => page_path.clone(),
+                        };
+                        let link = md::Tag::Link(
+                            md::LinkType::Inline,
+                            encode_href(&index_link).into(),
+                            alias.clone().into(),
+                        );
+
+                        contents.push(md::Event::Start(md::Tag::Item));
+                        contents.push(md::Event::Start(link.clone()));
+                        contents.push(md::Event::Code(
+                            self.display_name(&alias, &alias).to_string().into(),
+                        ));
+                        contents.push(md::Event::End(link));
+                        self.push_count(&mut contents, tags.len());
+                        contents.push(md::Event::End(md::Tag::Item));
+                    }
+
+                    let page_path = format!("./{}", page_path);
+                    let mut tag_contents =
+                        vec![md::Event::Start(md::Tag::Header(self.header_level(1)))];
+                    tag_contents.extend(self.tag_name(&alias, &alias));
+                    tag_contents.push(md::Event::End(md::Tag::Header(self.header_level(1))));
+                    self.push_tag_section(
+                        &mut tag_contents,
+                        &alias,
+                        tags,
+                        &path_to_root(&page_path),
+                    )?;
+
+                    // the sidebar shows our tag the way its header does
+                    sub_items.push(BookItem::Chapter(Chapter {
+                        name: self.display_name(&alias, &alias).to_string(),
+                        content: to_markdown(&tag_contents)?,
+                        number: None,
+                        sub_items: vec![],
+                        path: page_path.into(),
+                        parent_names: vec![self.title.clone()],
+                    }));
+                }
+                contents.push(md::Event::End(md::Tag::List(None)));
+            }
+        }
+
+        if let Some(BookItem::Chapter(all_tags)) = sub_items.first() {
+            if self.mode == Mode::Single {
+                let link = md::Tag::Link(
+                    md::LinkType::Inline,
+                    self.sub_page_href(ALL_TAGS_FILENAME).into(),
+                    all_tags.name.clone().into(),
+                );
+
+                contents.push(md::Event::Start(md::Tag::Paragraph));
+                contents.push(md::Event::Start(link.clone()));
+                contents.push(md::Event::Text(all_tags.name.clone().into()));
+                contents.push(md::Event::End(link));
+                contents.push(md::Event::End(md::Tag::Paragraph));
+            }
+        }
+
+        writer.write_all(to_markdown(&contents)?.as_bytes())?;
+        Ok(sub_items)
+    }
+
+    /// Our `section_template` filled in for a single tag, it's written to our page as is
+    fn fill_section_template(
+        &self,
+        template: &str,
+        alias: &str,
+        tags: &[Tag],
+        root: &str,
+    ) -> Result<String> {
+        let mut chapters = vec![];
+        self.push_tag_section(&mut chapters, alias, tags.to_vec(), root)?;
+
+        let mut section = fill_template(
+            template,
+            &[
+                ("alias", escape_html(self.display_name(alias, alias))),
+                ("count", tags.len().to_string()),
+                ("chapters", to_markdown(&chapters)?),
+            ],
+        );
+        if !section.ends_with('\n') {
+            section.push('\n');
+        }
+
+        Ok(section)
+    }
+
+    /// The raw markdown of a definition list entry for our tag, pulldown-cmark can't model them. Our
+    /// tag is the term, followed by its description and its chapters as definitions.
+    fn tag_definition(&self, alias: &str, name: &str, tags: &[Tag], root: &str) -> Result<String> {
+        let mut term = self.tag_name(alias, name);
+        self.push_count(&mut term, tags.len());
+
+        let chapters = self.chapter_links(tags, root)?;
+
+        let mut definition = format!("{}\n", to_markdown(&term)?);
+        if let Some(description) = self.descriptions.get(alias) {
+            // each definition has to stay on its own line, so only our first paragraph fits
+            let paragraph = description
+                .trim()
+                .lines()
+                .take_while(|line| !line.trim().is_empty())
+                .collect::<Vec<_>>()
+                .join(" ");
+            definition.push_str(&format!(": {}\n", paragraph));
+        }
+        definition.push_str(&format!(": {}\n", to_markdown(&chapters)?));
+
+        Ok(definition)
+    }
+
+    /// A comma separated link to each of the chapters of a tag, in our chapter order and truncated
+    /// to our `max_chapters_per_tag`. Drafts have nothing to link to so they're only named.
+    fn chapter_links(&self, tags: &[Tag], root: &str) -> Result<Vec<md::Event<'static>>> {
+        let mut tags = tags.to_vec();
+        sort_chapters(&mut tags, self.sort_chapters);
+        let hidden = self.truncate_chapters(&mut tags);
+
+        let mut links = vec![];
+        for tag in tags.into_iter() {
+            if !links.is_empty() {
+                links.push(md::Event::Text(", ".into()));
+            }
+
+            let chapter_name = self.chapter_link_name(&tag.chapter_name);
+            if is_draft(&tag.path) {
+                links.push(md::Event::Text(chapter_name.into()));
+                continue;
+            }
+
+            let link = md::Tag::Link(
+                md::LinkType::Inline,
+                chapter_href(&tag.path, tag.anchor.as_deref(), root)?.into(),
+                chapter_name.clone().into(),
+            );
+            links.push(md::Event::Start(link.clone()));
+            links.push(md::Event::Text(chapter_name.into()));
+            links.push(md::Event::End(link));
+        }
+        if hidden > 0 {
+            links.push(md::Event::Text(format!(", +{} more", hidden).into()));
+        }
+
+        Ok(links)
+    }
+
+    /// Link to the tags sharing chapters with one of our tags, right under its header
+    fn push_related(
+        &self,
+        contents: &mut Vec<md::Event>,
+        related: &[String],
+        anchors: &TagAnchors,
+        root: &str,
+    ) {
+        contents.push(md::Event::Start(md::Tag::Paragraph));
+        contents.push(md::Event::Start(md::Tag::Strong));
+        contents.push(md::Event::Text("Related:".into()));
+        contents.push(md::Event::End(md::Tag::Strong));
+        contents.push(md::Event::Text(" ".into()));
+        for (i, alias) in related.iter().enumerate() {
+            if i > 0 {
+                contents.push(md::Event::Text(", ".into()));
+            }
+
+            let name = self.display_name(alias, alias).to_string();
+            let link = md::Tag::Link(
+                md::LinkType::Inline,
+                format!("{}{}", root, self.tag_href(alias, anchors)).into(),
+                name.clone().into(),
+            );
+            contents.push(md::Event::Start(link.clone()));
+            contents.push(md::Event::Text(name.into()));
+            contents.push(md::Event::End(link));
+        }
+        contents.push(md::Event::End(md::Tag::Paragraph));
+    }
+
+    /// Push a paragraph linking to the anchor of every tag on our page, in the order they're listed
+    fn push_jump_bar(
+        &self,
+        contents: &mut Vec<md::Event>,
+        sorted_tags: &[(String, Vec<Tag>)],
+        anchors: &TagAnchors,
+    ) {
+        let mut aliases = sorted_tags
+            .iter()
+            .map(|(alias, _)| alias)
+            .collect::<Vec<_>>();
+        if self.group_by == GroupBy::Letter {
+            aliases.sort_by_key(|alias| letter_group(alias));
+        }
+
+        contents.push(md::Event::Start(md::Tag::Paragraph));
+        for (i, alias) in aliases.into_iter().enumerate() {
+            if i > 0 {
+                contents.push(md::Event::Text(" · ".into()));
+            }
+
+            let name = self.display_name(alias, alias).to_string();
+            let link = md::Tag::Link(
+                md::LinkType::Inline,
+                format!("#{}", self.tag_anchor_id(alias, anchors)).into(),
+                name.clone().into(),
+            );
+            contents.push(md::Event::Start(link.clone()));
+            contents.push(md::Event::Text(name.into()));
+            contents.push(md::Event::End(link));
+        }
+        contents.push(md::Event::End(md::Tag::Paragraph));
+    }
+
+    /// Push a table with a row for each of our tags, starting a new table for each letter group
+    fn push_tags_table<'a>(
+        &'a self,
+        contents: &mut Vec<md::Event<'a>>,
+        mut sorted_tags: Vec<(String, Vec<Tag>)>,
+        anchors: &TagAnchors,
+        root: &str,
+    ) -> Result<()> {
+        if self.group_by == GroupBy::Letter {
+            sorted_tags.sort_by_key(|(alias, _)| letter_group(alias));
+        }
+
+        let columns = vec![md::Alignment::None, md::Alignment::None];
+        let push_cell = |contents: &mut Vec<md::Event<'a>>, cell: Vec<md::Event<'a>>| {
+            contents.push(md::Event::Start(md::Tag::TableCell));
+            contents.extend(cell.into_iter().map(escape_cell));
+            contents.push(md::Event::End(md::Tag::TableCell));
+        };
+
+        let mut current_group = None;
+        let mut open_table = false;
+        for (alias, tags) in sorted_tags {
+            if self.group_by == GroupBy::Letter {
+                let group = Some(letter_group(&alias));
+                if group != current_group {
+                    if open_table {
+                        contents.push(md::Event::End(md::Tag::Table(columns.clone())));
+                        open_table = false;
+                    }
+
+                    let heading = match group {
+                        Some(Some(letter)) => letter.to_string(),
+                        _ => "#".to_string(),
+                    };
+
+                    contents.push(md::Event::Start(md::Tag::Header(self.header_level(1))));
+                    contents.push(md::Event::Text(heading.into()));
+                    contents.push(md::Event::End(md::Tag::Header(self.header_level(1))));
+                    current_group = group;
+                }
+            }
+
+            if !open_table {
+                contents.push(md::Event::Start(md::Tag::Table(columns.clone())));
+                contents.push(md::Event::Start(md::Tag::TableHead));
+                push_cell(contents, vec![md::Event::Text("Tag".into())]);
+                push_cell(contents, vec![md::Event::Text("Chapters".into())]);
+                contents.push(md::Event::End(md::Tag::TableHead));
+                open_table = true;
+            }
+
+            // our anchor lives in the row so links to this tag still resolve
+            let mut tag_cell = vec![md::Event::InlineHtml(
+                format!(
+                    "<a id=\"{}\"></a>",
+                    escape_html(&self.tag_anchor_id(&alias, anchors))
+                )
+                .into(),
+            )];
+            tag_cell.extend(self.tag_name(&alias, &alias));
+            self.push_count(&mut tag_cell, tags.len());
+            let chapters_cell = self.chapter_links(&tags, root)?;
+
+            contents.push(md::Event::Start(md::Tag::TableRow));
+            push_cell(contents, tag_cell);
+            push_cell(contents, chapters_cell);
+            contents.push(md::Event::End(md::Tag::TableRow));
+        }
+
+        if open_table {
+            contents.push(md::Event::End(md::Tag::Table(columns)));
+        }
+
+        Ok(())
+    }
+
+    /// The chapter a tag is defined in, either from our `primary` table or the first chapter by
+    /// path when `primary_auto` is set
+    fn primary_tag<'t>(&self, alias: &str, tags: &'t [Tag]) -> Option<&'t Tag> {
+        let linkable = || tags.iter().filter(|tag| !is_draft(&tag.path));
+
+        let configured = self.primary.get(alias).and_then(|primary| {
+            linkable().find(|tag| strip_current_dir(&tag.path) == strip_current_dir(primary))
+        });
+
+        match configured {
+            Some(tag) => Some(tag),
+            None if self.primary_auto => linkable().min_by(|a, b| a.path.cmp(&b.path)),
+            None => None,
+        }
+    }
+
+    fn push_count(&self, contents: &mut Vec<md::Event>, count: usize) {
+        if self.show_counts {
+            contents.push(md::Event::Text(format!(" ({})", count).into()));
+        }
+    }
+
+    /// Add our tags page to the book, separated from the rest of our chapters
+    fn insert_tags_page(&self, book: &mut Book, mut tags_page: Chapter) {
+        // marked so a rebuild removes our page, and only our page
+        tags_page.content = format!("{}\n\n{}", GENERATED_MARKER, tags_page.content);
+
+        // mdbook lists every chapter in the sidebar, so tucking our page under a parent is the
+        // closest we can get to hiding it
+        if let Some(parent_name) = &self.parent {
+            match find_chapter_mut(&mut book.sections, parent_name) {
+                Some(parent) => {
+                    tags_page.parent_names = parent.parent_names.clone();
+                    tags_page.parent_names.push(parent.name.clone());
+
+                    let item = BookItem::Chapter(tags_page);
+                    match self.position {
+                        Position::Start => parent.sub_items.insert(0, item),
+                        Position::End => parent.sub_items.push(item),
+                    }
+
+                    return;
+                }
+                None => warn!(
+                    "Couldn't find the parent chapter \"{}\" for our tags page",
+                    parent_name
+                ),
+            }
+        }
+
+        let mut items = vec![BookItem::Chapter(tags_page)];
+        match self.position {
+            Position::Start => {
+                if self.separator {
+                    items.push(BookItem::Separator);
+                }
+                book.sections.splice(0..0, items);
+            }
+            Position::End => {
+                if self.separator {
+                    items.insert(0, BookItem::Separator);
+                }
+                book.sections.extend(items);
+            }
+        }
+    }
+
+    /// The most recent modification time of any of the chapters using our tag
+    fn last_modified(&self, tags: &[Tag]) -> Option<SystemTime> {
+        tags.iter()
+            .filter_map(|tag| self.modified.get(strip_current_dir(&tag.path)))
+            .max()
+            .copied()
+    }
+
+    /// Our header `level` shifted down by `heading_offset`, there are only 6 levels of headers
+    fn header_level(&self, level: usize) -> i32 {
+        (level + self.heading_offset).min(6) as i32
+    }
+
+    /// How often a tag is used, multiplied by its configured weight
+    fn weighted_count(&self, alias: &str, tags: &[Tag]) -> usize {
+        tags.len() * self.weights.get(alias).copied().unwrap_or(1)
+    }
+
+    /// The name we show for an alias, its configured display name or `name` as it's written
+    /// without any of our `strip_display_prefix`
+    fn display_name<'a>(&'a self, alias: &str, name: &'a str) -> &'a str {
+        match self.display_names.get(alias) {
+            Some(display_name) => display_name,
+            None => self.strip_display_prefix(name),
+        }
+    }
+
+    /// Our `name` without the first of our display prefixes it starts with, unless that's all of it
+    fn strip_display_prefix<'a>(&self, name: &'a str) -> &'a str {
+        for prefix in self.strip_display_prefix.iter() {
+            let matches = match name.get(..prefix.len()) {
+                Some(start) if self.case_sensitive => start == prefix,
+                Some(start) => start.to_lowercase() == prefix.to_lowercase(),
+                None => false,
+            };
+            if matches && name.len() > prefix.len() {
+                return &name[prefix.len()..];
+            }
+        }
+
+        name
+    }
+
+    /// The code showing an alias on our tags page, linking to its url if it's an external tag
+    fn tag_name(&self, alias: &str, name: &str) -> Vec<md::Event<'static>> {
+        let code = md::Event::Code(self.display_name(alias, name).to_string().into());
+
+        match self.external.get(alias) {
+            Some(url) => {
+                let link = md::Tag::Link(
+                    md::LinkType::Inline,
+                    url.clone().into(),
+                    alias.to_string().into(),
+                );
+
+                self.badge(
+                    alias,
+                    vec![md::Event::Start(link.clone()), code, md::Event::End(link)],
+                )
+            }
+            None => self.badge(alias, vec![code]),
+        }
+    }
+
+    /// Wrap our events in a colored badge if our tag, or the closest of its parents, has a color
+    fn badge<'a>(&self, alias: &str, mut events: Vec<md::Event<'a>>) -> Vec<md::Event<'a>> {
+        let mut colored = alias;
+        let color = loop {
+            if let Some(color) = self.colors.get(colored) {
+                break color;
+            }
+            match colored.rfind('/') {
+                Some(parent) => colored = &colored[..parent],
+                None => return events,
+            }
+        };
+
+        events.insert(
+            0,
+            md::Event::InlineHtml(
+                format!(
+                    r#"<span class="tag-badge" style="background: {}">"#,
+                    escape_html(color)
+                )
+                .into(),
+            ),
+        );
+        events.push(md::Event::InlineHtml("</span>".into()));
+
+        events
+    }
+
+    /// Push the description and chapter links for a single tag, `root` is prepended to each link
+    /// so it resolves from wherever this section ends up
+    fn push_tag_section<'a>(
+        &'a self,
+        contents: &mut Vec<md::Event<'a>>,
+        alias: &str,
+        mut tags: Vec<Tag>,
+        root: &str,
+    ) -> Result<()> {
+        if let Some(description) = self.descriptions.get(alias) {
+            contents.extend(new_cmark_parser(description));
+        }
+
+        if let Some(primary) = self.primary_tag(alias, &tags) {
+            let chapter_name = self.chapter_link_name(&primary.chapter_name);
+            let link = md::Tag::Link(
+                md::LinkType::Inline,
+                chapter_href(&primary.path, primary.anchor.as_deref(), root)?.into(),
+                chapter_name.clone().into(),
+            );
+
+            contents.push(md::Event::Start(md::Tag::Paragraph));
+            contents.push(md::Event::Start(md::Tag::Strong));
+            contents.push(md::Event::Text("Defined in:".into()));
+            contents.push(md::Event::End(md::Tag::Strong));
+            contents.push(md::Event::Text(" ".into()));
+            contents.push(md::Event::Start(link.clone()));
+            contents.push(md::Event::Text(chapter_name.into()));
+            contents.push(md::Event::End(link));
+            contents.push(md::Event::End(md::Tag::Paragraph));
+        }
+
+        sort_chapters(&mut tags, self.sort_chapters);
+        let hidden = self.truncate_chapters(&mut tags);
+
+        let inline = self.chapter_layout == ChapterLayout::Inline;
+        if inline {
+            contents.push(md::Event::Start(md::Tag::Paragraph));
+        } else {
+            contents.push(md::Event::Start(md::Tag::List(None)));
+        }
+        for (
+            i,
+            Tag {
+                chapter_name,
+                path,
+                parent_names,
+                anchor,
+                snippet,
+                ..
+            },
+        ) in tags.into_iter().enumerate()
+        {
+            let parent_path: String = if !parent_names.is_empty() {
+                format!("/{}/", parent_names.join("/"))
+            } else {
+                "/".into()
+            };
+
+            if !inline {
+                contents.push(md::Event::Start(md::Tag::Item));
+            } else if i > 0 {
+                contents.push(md::Event::Text(", ".into()));
+            }
+            contents.push(md::Event::Text(parent_path.into()));
+
+            let chapter_name = self.chapter_link_name(&chapter_name);
+            if is_draft(&path) {
+                contents.push(md::Event::Text(chapter_name.into()));
+                if !inline {
+                    contents.push(md::Event::End(md::Tag::Item));
+                }
+                continue;
+            }
+
+            let link = md::Tag::Link(
+                md::LinkType::Inline,
+                chapter_href(&path, anchor.as_deref(), root)?.into(),
+                chapter_name.clone().into(),
+            );
+
+            contents.push(md::Event::Start(link.clone()));
+            contents.push(md::Event::Text(chapter_name.into()));
+            contents.push(md::Event::End(link));
+            if let Some(snippet) = snippet {
+                // our inline chapters share a line, so the snippet can't go on its own
+                contents.push(if inline {
+                    md::Event::Text(" ".into())
+                } else {
+                    md::Event::HardBreak
+                });
+                contents.push(md::Event::Start(md::Tag::Emphasis));
+                contents.push(md::Event::Text(snippet.into()));
+                contents.push(md::Event::End(md::Tag::Emphasis));
+            }
+            if !inline {
+                contents.push(md::Event::End(md::Tag::Item));
+            }
+        }
+        if hidden > 0 {
+            let more = md::Event::Text(format!("+{} more", hidden).into());
+            if inline {
+                contents.push(md::Event::Text(", ".into()));
+                contents.push(more);
+            } else {
+                contents.push(md::Event::Start(md::Tag::Item));
+                contents.push(more);
+                contents.push(md::Event::End(md::Tag::Item));
+            }
+        }
+        if inline {
+            contents.push(md::Event::End(md::Tag::Paragraph));
+        } else {
+            contents.push(md::Event::End(md::Tag::List(None)));
+        }
+
+        Ok(())
+    }
+
+    /// Only keep the first `max_chapters_per_tag` of our sorted chapters on a single tags page,
+    /// returning how many we left off. Per tag pages always list every chapter.
+    fn truncate_chapters(&self, tags: &mut Vec<Tag>) -> usize {
+        match self.max_chapters_per_tag {
+            Some(max) if self.mode == Mode::Single && tags.len() > max => {
+                let hidden = tags.len() - max;
+                tags.truncate(max);
+
+                hidden
+            }
+            _ => 0,
+        }
+    }
+
+    /// Write an Atom feed with an entry for each of our tagged chapters, the most recently modified
+    /// first. Our links are relative to the root of the rendered book.
+    fn write_atom<W: Write>(
+        &self,
+        tags_map: &HashMap<String, Vec<Tag>>,
+        modified: &HashMap<PathBuf, SystemTime>,
+        mut writer: W,
+    ) -> Result<()> {
+        let mut chapters: BTreeMap<&Path, (&str, BTreeSet<&str>)> = BTreeMap::new();
+        for (alias, tags) in tags_map.iter() {
+            for tag in tags.iter().filter(|tag| !is_draft(&tag.path)) {
+                chapters
+                    .entry(strip_current_dir(&tag.path))
+                    .or_insert_with(|| (&tag.chapter_name, BTreeSet::new()))
+                    .1
+                    .insert(alias);
+            }
+        }
+
+        let now = SystemTime::now();
+        let mut entries = chapters
+            .into_iter()
+            .map(|(path, chapter)| (path, modified.get(path).copied().unwrap_or(now), chapter))
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|(_, updated, _)| Reverse(*updated));
+        let feed_updated = entries.first().map_or(now, |(_, updated, _)| *updated);
+
+        // feed readers tell feeds and their entries apart by id, so they need to be unique to our book
+        let id = |href: &str| match (&self.base_url, &self.book_title) {
+            (Some(base_url), _) => format!("{}/{}", base_url.trim_end_matches('/'), href),
+            (None, Some(book_title)) => format!("urn:mdbook-tag:{}:{}", slugify(book_title), href),
+            (None, None) => format!("urn:mdbook-tag:{}", href),
+        };
+        // every feed needs an author, without any our book stands in for them
+        let authors = if self.authors.is_empty() {
+            vec![self
+                .book_title
+                .clone()
+                .unwrap_or_else(|| self.title.clone())]
+        } else {
+            self.authors.clone()
+        };
+        let page_href = escape_html(&encode_href(&html_path(Path::new(&self.page_path()))));
+
+        writeln!(writer, r#"<?xml version="1.0" encoding="utf-8"?>"#)?;
+        writeln!(writer, r#"<feed xmlns="http://www.w3.org/2005/Atom">"#)?;
+        writeln!(writer, "  <title>{}</title>", escape_html(&self.title))?;
+        writeln!(writer, "  <id>{}</id>", id(&page_href))?;
+        writeln!(writer, "  <updated>{}</updated>", rfc3339(feed_updated))?;
+        for author in authors {
+            writeln!(writer, "  <author>")?;
+            writeln!(writer, "    <name>{}</name>", escape_html(&author))?;
+            writeln!(writer, "  </author>")?;
+        }
+        writeln!(writer, r#"  <link href="{}"/>"#, page_href)?;
+        for (path, updated, (chapter_name, aliases)) in entries {
+            let href = escape_html(&encode_href(&html_path(path)));
+
+            writeln!(writer, "  <entry>")?;
+            writeln!(writer, "    <title>{}</title>", escape_html(chapter_name))?;
+            writeln!(writer, "    <id>{}</id>", id(&href))?;
+            writeln!(writer, r#"    <link href="{}"/>"#, href)?;
+            writeln!(writer, "    <updated>{}</updated>", rfc3339(updated))?;
+            for alias in aliases {
+                writeln!(writer, r#"    <category term="{}"/>"#, escape_html(alias))?;
+            }
+            writeln!(writer, "  </entry>")?;
+        }
+        writeln!(writer, "</feed>")?;
+
+        Ok(())
+    }
+
+    /// Write a sitemap with a url for the anchor of each of our tags, under our `base_url`
+    fn write_sitemap<W: Write>(
+        &self,
+        tags_map: &HashMap<String, Vec<Tag>>,
+        anchors: &TagAnchors,
+        mut writer: W,
+    ) -> Result<()> {
+        let base_url = self.base_url.as_deref().unwrap_or_default();
+        let mut aliases = tags_map.keys().collect::<Vec<_>>();
+        aliases.sort_by_cached_key(|alias| collation_key(alias));
+
+        writeln!(writer, r#"<?xml version="1.0" encoding="utf-8"?>"#)?;
+        writeln!(
+            writer,
+            r#"<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">"#
+        )?;
+        for alias in aliases {
+            let href = self.tag_href(alias, anchors);
+            // our per tag pages are linked to directly, without a fragment
+            let loc = match href.split_once('#') {
+                Some((page, fragment)) => format!(
+                    "{}/{}#{}",
+                    base_url.trim_end_matches('/'),
+                    html_path(Path::new(page)),
+                    fragment
+                ),
+                None => format!(
+                    "{}/{}",
+                    base_url.trim_end_matches('/'),
+                    html_path(Path::new(&href))
+                ),
+            };
+
+            writeln!(writer, "  <url>")?;
+            writeln!(writer, "    <loc>{}</loc>", escape_html(&loc))?;
+            writeln!(writer, "  </url>")?;
+        }
+        writeln!(writer, "</urlset>")?;
+
+        Ok(())
+    }
+
+    /// The name of a chapter in the links on our tags page, after our `chapter_name_transform`
+    fn chapter_link_name(&self, chapter_name: &str) -> String {
+        match self.chapter_name_transform {
+            ChapterNameTransform::None => chapter_name.into(),
+            ChapterNameTransform::StripNumbering => strip_numbering(chapter_name).into(),
+        }
+    }
+
+    /// Combine the tags of several books into one map, the chapters of aliases found in more than
+    /// one book are listed together in the order of our `maps`
+    pub fn merge_tag_maps(maps: Vec<HashMap<String, Vec<Tag>>>) -> HashMap<String, Vec<Tag>> {
+        let mut merged: HashMap<String, Vec<Tag>> = HashMap::new();
+        for map in maps {
+            for (alias, tags) in map {
+                merged.entry(alias).or_default().extend(tags);
+            }
+        }
+
+        merged
+    }
+
+    /// Read the tags of another book from its `json_output`. Each chapter's path is prefixed with
+    /// `book_path`, the location of that book's source relative to ours, so our links reach it.
+    pub fn read_json<R: Read>(reader: R, book_path: &Path) -> Result<HashMap<String, Vec<Tag>>> {
+        let mut tags_map: HashMap<String, Vec<Tag>> = serde_json::from_reader(reader)?;
+        for tags in tags_map.values_mut() {
+            // drafts have no file in the other book to link to
+            tags.retain(|tag| !is_draft(&tag.path));
+            for tag in tags.iter_mut() {
+                tag.path = book_path.join(strip_current_dir(&tag.path));
+            }
+        }
+        tags_map.retain(|_, tags| !tags.is_empty());
+
+        Ok(tags_map)
+    }
+
+    /// Write all of our tags as JSON, ordered the same way as our tags page so the output is stable
+    fn write_json<W: Write>(&self, tags_map: &HashMap<String, Vec<Tag>>, writer: W) -> Result<()> {
+        let sorted_tags = tags_map
+            .iter()
+            .map(|(alias, tags)| {
+                let mut tags = tags.clone();
+                sort_chapters(&mut tags, self.sort_chapters);
+
+                (alias, tags)
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        serde_json::to_writer_pretty(writer, &sorted_tags)?;
+
+        Ok(())
+    }
+
+    /// The id of the anchor for our alias on our tags page, starting with our `anchor_prefix`
+    fn tag_anchor_id(&self, alias: &str, anchors: &TagAnchors) -> String {
+        format!("{}{}", self.anchor_prefix, anchor_id(alias, anchors))
+    }
+
+    /// The link to a tag relative to the root of our book
+    fn tag_href(&self, alias: &str, anchors: &TagAnchors) -> String {
+        match self.mode {
+            // tags that aren't featured are only on our page of every tag
+            Mode::Single
+                if !self.featured.is_empty() && !self.featured.iter().any(|f| f == alias) =>
+            {
+                format!(
+                    "{}#{}",
+                    encode_href(&self.all_tags_tagger().page_path()),
+                    encode_href(&self.tag_anchor_id(alias, anchors))
+                )
+            }
+            Mode::Single if self.split == Split::AlphaRanges => {
+                let letter = letter_group(alias);
+                let ranges = self.letter_ranges(anchors);
+                let letters = ranges
+                    .iter()
+                    .find(|letters| letters.contains(&letter))
+                    .map_or_else(|| vec![letter], Clone::clone);
+
+                format!(
+                    "{}#{}",
+                    encode_href(&self.range_tagger(&letters).page_path()),
+                    encode_href(&self.tag_anchor_id(alias, anchors))
+                )
+            }
+            Mode::Single => format!(
+                "{}#{}",
+                encode_href(&self.page_path()),
+                encode_href(&self.tag_anchor_id(alias, anchors))
+            ),
+            Mode::PerTag => encode_href(&self.tag_page_path(alias, anchors)),
+        }
+    }
+
+    /// Give every tag, and every parent of our nested tags, a unique anchor. Tags that slugify to
+    /// the same id are given a `-2`, `-3`, etc. suffix in alphabetical order.
+    pub fn tag_anchors(&self, tags_map: &HashMap<String, Vec<Tag>>) -> TagAnchors {
+        let mut aliases = BTreeSet::new();
+        for alias in tags_map.keys() {
+            let segments = alias.split('/').collect::<Vec<_>>();
+            for depth in 1..=segments.len() {
+                aliases.insert(segments[..depth].join("/"));
+            }
+        }
+
+        let mut used = HashSet::new();
+        // our back to top links need an anchor no tag can take, our prefix already keeps our tags
+        // off of it
+        if self.back_to_top && self.anchor_prefix.is_empty() {
+            used.insert(TOP_ANCHOR.to_string());
+        }
+        // our slugs step around the anchors that were chosen by hand
+        used.extend(self.custom_anchors.values().cloned());
+        aliases
+            .into_iter()
+            .map(|alias| {
+                if let Some(anchor) = self.custom_anchors.get(&alias) {
+                    return (alias, anchor.clone());
+                }
+                let slug = slugify(&alias);
+
+                let mut id = slug.clone();
+                let mut count = 2;
+                while !used.insert(id.clone()) {
+                    id = format!("{}-{}", slug, count);
+                    count += 1;
+                }
+
+                (alias, id)
+            })
+            .collect()
+    }
+
+    /// The path of our tags page relative to the root of our book
+    fn page_path(&self) -> String {
+        match &self.directory {
+            Some(directory) => format!("{}/{}", directory, self.output_filename),
+            None => self.output_filename.clone(),
+        }
+    }
+
+    /// Whether our tags page has pages of its own nested under it
+    fn has_sub_pages(&self) -> bool {
+        self.mode == Mode::PerTag || !self.featured.is_empty() || self.split != Split::None
+    }
+
+    /// Our tagger for a page nested under our tags page, these live in a directory named after our
+    /// output file, `tags.md` -> `tags/<filename>`
+    fn sub_page_tagger(&self, filename: String, title: String) -> Tagger {
+        let directory = Path::new(&self.page_path()).with_extension("");
+
+        Tagger {
+            output_filename: filename,
+            directory: Some(link_path(&directory)),
+            title,
+            preamble: None,
+            featured: vec![],
+            split: Split::None,
+            ..self.clone()
+        }
+    }
+
+    /// The link from our tags page to one of its nested pages, they share a directory so this is
+    /// relative to our page
+    fn sub_page_href(&self, filename: &str) -> String {
+        let href = Path::new(&self.output_filename)
+            .with_extension("")
+            .join(filename);
+
+        encode_href(&link_path(&href))
+    }
+
+    /// Our tagger for the page of every tag when only our featured tags are on our tags page,
+    /// `tags.md` -> `tags/all.md`
+    fn all_tags_tagger(&self) -> Tagger {
+        self.sub_page_tagger(ALL_TAGS_FILENAME.into(), format!("All {}", self.title))
+    }
+
+    /// Our tagger for the page of a range of letters, `tags.md` -> `tags/a-f.md`
+    fn range_tagger(&self, letters: &[Option<char>]) -> Tagger {
+        let name = |letter: Option<char>| letter.map_or("#".into(), |l| l.to_string());
+        let file = |letter: Option<char>| {
+            letter.map_or("symbols".into(), |l| l.to_lowercase().to_string())
+        };
+
+        let first = letters.first().copied().flatten();
+        let last = letters.last().copied().flatten();
+        if letters.len() > 1 {
+            self.sub_page_tagger(
+                format!("{}-{}.md", file(first), file(last)),
+                format!("{}–{}", name(first), name(last)),
+            )
+        } else {
+            self.sub_page_tagger(format!("{}.md", file(first)), name(first))
+        }
+    }
+
+    /// The ranges of letters of our split pages, unless `tag_book` already found them
+    fn letter_ranges(&self, anchors: &TagAnchors) -> Cow<'_, [Vec<Option<char>>]> {
+        match &self.letter_ranges {
+            Some(ranges) => Cow::Borrowed(ranges),
+            None => Cow::Owned(self.alpha_ranges(anchors.keys())),
+        }
+    }
+
+    /// Group the letters of our aliases into ranges with at most `split_size` aliases each, a
+    /// letter is never split across ranges so it may have more by itself
+    fn alpha_ranges<'a, I>(&self, aliases: I) -> Vec<Vec<Option<char>>>
+    where
+        I: IntoIterator<Item = &'a String>,
+    {
+        let mut counts = BTreeMap::new();
+        for alias in aliases {
+            *counts.entry(letter_group(alias)).or_insert(0) += 1;
+        }
+
+        let mut ranges: Vec<(Vec<Option<char>>, usize)> = vec![];
+        for (letter, count) in counts {
+            match ranges.last_mut() {
+                Some((letters, size)) if *size + count <= self.split_size => {
+                    letters.push(letter);
+                    *size += count;
+                }
+                _ => ranges.push((vec![letter], count)),
+            }
+        }
+
+        ranges.into_iter().map(|(letters, _)| letters).collect()
+    }
+
+    /// The path of an individual tag's page when using `Mode::PerTag`, these live in a directory
+    /// named after our output file, `tags.md` -> `tags/<alias>.md`
+    fn tag_page_path(&self, alias: &str, anchors: &TagAnchors) -> String {
+        let directory = Path::new(&self.page_path()).with_extension("");
+
+        format!("{}/{}.md", link_path(&directory), anchor_id(alias, anchors))
+    }
+}
+
+/// How the aliases our authors write are turned into the tags we collect
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Normalize {
+    /// Lowercase the alias, unless we're `case_sensitive`
+    Lower,
+    /// `Machine Learning!` -> `machine-learning`
+    Slug,
+}
+
+impl Normalize {
+    const VALUES: &'static [&'static str] = &["lower", "slug"];
+
+    fn from_str(normalize: &str) -> Option<Normalize> {
+        match normalize {
+            "lower" => Some(Normalize::Lower),
+            "slug" => Some(Normalize::Slug),
+            _ => None,
+        }
+    }
+}
+
+/// What happens to the spaces within an alias, like `machine learning`
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum AliasSpaces {
+    /// Leave them in the alias
+    Keep,
+    /// Fail the build
+    Error,
+    /// Replace each run of spaces with a `-`
+    Hyphenate,
+}
+
+impl AliasSpaces {
+    const VALUES: &'static [&'static str] = &["keep", "error", "hyphenate"];
+
+    fn from_str(alias_spaces: &str) -> Option<AliasSpaces> {
+        match alias_spaces {
+            "keep" => Some(AliasSpaces::Keep),
+            "error" => Some(AliasSpaces::Error),
+            "hyphenate" => Some(AliasSpaces::Hyphenate),
+            _ => None,
+        }
+    }
+}
+
+/// What the inline code of our tag links shows
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Display {
+    /// `#rust`
+    Hash,
+    /// `rust`
+    Name,
+    /// `rust #`
+    Both,
+}
+
+impl Display {
+    const VALUES: &'static [&'static str] = &["hash", "name", "both"];
+
+    fn from_str(display: &str) -> Option<Display> {
+        match display {
+            "hash" => Some(Display::Hash),
+            "name" => Some(Display::Name),
+            "both" => Some(Display::Both),
+            _ => None,
+        }
+    }
+}
+
+/// How the tag links in our chapters are written
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum LinkStyle {
+    /// `[#rust](tags.md#rust "Tag: rust")`
+    Inline,
+    /// `[#rust][tag:rust]` with a `[tag:rust]: tags.md#rust "Tag: rust"` definition at the bottom
+    /// of the chapter
+    Reference,
+}
+
+impl LinkStyle {
+    const VALUES: &'static [&'static str] = &["inline", "reference"];
+
+    fn from_str(link_style: &str) -> Option<LinkStyle> {
+        match link_style {
+            "inline" => Some(LinkStyle::Inline),
+            "reference" => Some(LinkStyle::Reference),
+            _ => None,
+        }
+    }
+}
+
+/// What happens to tags in our chapters that are used too rarely to be on our tags page
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RareInline {
+    /// Still link the tag to the top of our tags page
+    Link,
+    /// Show the tag without linking it
+    Text,
+}
+
+impl RareInline {
+    const VALUES: &'static [&'static str] = &["link", "text"];
+
+    fn from_str(rare_inline: &str) -> Option<RareInline> {
+        match rare_inline {
+            "link" => Some(RareInline::Link),
+            "text" => Some(RareInline::Text),
+            _ => None,
+        }
+    }
+}
+
+/// How the chapters under each tag are listed on our tags page
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ChapterLayout {
+    /// A bullet list with a chapter per line
+    List,
+    /// A single paragraph of comma separated chapters
+    Inline,
+}
+
+impl ChapterLayout {
+    const VALUES: &'static [&'static str] = &["list", "inline"];
+
+    fn from_str(chapter_layout: &str) -> Option<ChapterLayout> {
+        match chapter_layout {
+            "list" => Some(ChapterLayout::List),
+            "inline" => Some(ChapterLayout::Inline),
+            _ => None,
+        }
+    }
+}
+
+/// The order of the chapters under each tag
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ChapterSort {
+    /// By their parent chapters and then their name
+    Name,
+    /// By their section number, the order they're read in
+    BookOrder,
+    /// By their name alone, regardless of their parent chapters
+    Title,
+}
+
+impl ChapterSort {
+    const VALUES: &'static [&'static str] = &["name", "book-order", "title"];
+
+    fn from_str(sort_chapters: &str) -> Option<ChapterSort> {
+        match sort_chapters {
+            "name" => Some(ChapterSort::Name),
+            "book-order" => Some(ChapterSort::BookOrder),
+            "title" => Some(ChapterSort::Title),
+            _ => None,
+        }
+    }
+}
+
+/// How the names of chapters are shown in the links on our tags page
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ChapterNameTransform {
+    /// Exactly as they're named in our summary
+    None,
+    /// Without any leading numbering like `1.` or `2.3.`
+    StripNumbering,
+}
+
+impl ChapterNameTransform {
+    const VALUES: &'static [&'static str] = &["none", "strip-numbering"];
+
+    fn from_str(transform: &str) -> Option<ChapterNameTransform> {
+        match transform {
+            "none" => Some(ChapterNameTransform::None),
+            "strip-numbering" => Some(ChapterNameTransform::StripNumbering),
+            _ => None,
+        }
+    }
+}
+
+/// How each tag is shown on a single tags page
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Layout {
+    /// A header per tag followed by its chapters
+    List,
+    /// A table with a row for each tag and its chapters
+    Table,
+    /// A header per tag followed by its description, without any chapters
+    Glossary,
+    /// A definition list with each tag as a term and its chapters as the definition
+    Deflist,
+}
+
+impl Layout {
+    const VALUES: &'static [&'static str] = &["list", "table", "glossary", "deflist"];
+
+    fn from_str(layout: &str) -> Option<Layout> {
+        match layout {
+            "list" => Some(Layout::List),
+            "table" => Some(Layout::Table),
+            "glossary" => Some(Layout::Glossary),
+            "deflist" => Some(Layout::Deflist),
+            _ => None,
+        }
+    }
+}
+
+/// What replaces the tags in our chapters
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Inline {
+    /// A link to the tag on our tags page
+    Link,
+    /// Nothing, the tag is removed from the chapter but still listed on our tags page
+    None,
+    /// The tag as a code span without a link, it's still listed on our tags page
+    Text,
+}
+
+impl Inline {
+    const VALUES: &'static [&'static str] = &["link", "none", "text"];
+
+    fn from_str(inline: &str) -> Option<Inline> {
+        match inline {
+            "link" => Some(Inline::Link),
+            "none" => Some(Inline::None),
+            "text" => Some(Inline::Text),
+            _ => None,
+        }
+    }
+}
+
+/// What happens to ignored tags in our chapters
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum IgnoreInline {
+    /// Show the tag without linking it
+    Text,
+    /// Remove the tag from the chapter
+    Remove,
+}
+
+impl IgnoreInline {
+    const VALUES: &'static [&'static str] = &["text", "remove"];
+
+    fn from_str(ignore_inline: &str) -> Option<IgnoreInline> {
+        match ignore_inline {
+            "text" => Some(IgnoreInline::Text),
+            "remove" => Some(IgnoreInline::Remove),
+            _ => None,
+        }
+    }
+}
+
+/// How our tags page is laid out
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Mode {
+    /// A single page containing every tag
+    Single,
+    /// An index page with a sub-page for each tag
+    PerTag,
+}
+
+impl Mode {
+    const VALUES: &'static [&'static str] = &["single", "per-tag"];
+
+    fn from_str(mode: &str) -> Option<Mode> {
+        match mode {
+            "single" => Some(Mode::Single),
+            "per-tag" => Some(Mode::PerTag),
+            _ => None,
+        }
+    }
+}
+
+/// The modification time of every chapter using one of our tags, skipping any that can't be read
+fn read_modified(src: &Path, tags: &HashMap<String, Vec<Tag>>) -> HashMap<PathBuf, SystemTime> {
+    let mut modified = HashMap::new();
+    for tag in tags.values().flatten() {
+        let path = strip_current_dir(&tag.path);
+        if is_draft(path) || modified.contains_key(path) {
+            continue;
+        }
+
+        match fs::metadata(src.join(path)).and_then(|metadata| metadata.modified()) {
+            Ok(time) => {
+                modified.insert(path.to_path_buf(), time);
+            }
+            Err(e) => debug!("Couldn't read when {} was modified: {}", path.display(), e),
+        }
+    }
+
+    modified
+}
+
+/// Order the chapters of a tag by their path in the book
+fn sort_chapters(tags: &mut [Tag], sort: ChapterSort) {
+    tags.sort_by_cached_key(|t| {
+        let mut sort_names = t.parent_names.clone();
+        sort_names.push(t.chapter_name.clone());
+
+        sort_names
+    });
+
+    if sort == ChapterSort::BookOrder {
+        // chapters without a number keep their order by name after the rest, our sort is stable
+        tags.sort_by(|a, b| match (&a.number, &b.number) {
+            (Some(a), Some(b)) => a.0.cmp(&b.0),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        });
+    } else if sort == ChapterSort::Title {
+        // chapters sharing a name keep their order by parents
+        tags.sort_by_cached_key(|t| t.chapter_name.clone());
+    }
+}
+
+/// Keep each tree of nested tags together where its first tag was sorted to, with the tags inside
+/// of it alphabetical, so each parent header is only listed once whatever our `sort` is
+fn group_nested(sorted_tags: &mut [(String, Vec<Tag>)]) {
+    let root = |alias: &str| alias.split('/').next().unwrap_or_default().to_string();
+
+    let mut roots = HashMap::new();
+    for (i, (alias, _)) in sorted_tags.iter().enumerate() {
+        roots.entry(root(alias)).or_insert(i);
+    }
+    sorted_tags.sort_by_cached_key(|(alias, _)| (roots[&root(alias)], collation_key(alias)));
+}
+
+/// The default silent prefix of a namespace, `topic:` becomes `topic!:`
+fn silent_prefix(prefix: &str) -> String {
+    match prefix.strip_suffix(':') {
+        Some(name) => format!("{}!:", name),
+        None => format!("{}!", prefix),
+    }
+}
+
+/// Every tag that's only used once along with the chapter it's in, sorted
+fn singletons(tags_map: &HashMap<String, Vec<Tag>>) -> Vec<String> {
+    let mut singletons = tags_map
+        .iter()
+        .filter(|(_, tags)| tags.len() == 1)
+        .map(|(alias, tags)| format!("\"{}\" in {}", alias, tags[0].path.display()))
+        .collect::<Vec<_>>();
+    singletons.sort();
+
+    singletons
+}
+
+/// The aliases of the tags in a chapter without any repeats, in the order they're first used
+fn distinct_aliases(tags: &[AliasedTag]) -> Vec<&str> {
+    let mut aliases = vec![];
+    for aliased in tags.iter() {
+        if !aliases.contains(&aliased.alias.as_str()) {
+            aliases.push(aliased.alias.as_str());
+        }
+    }
+
+    aliases
+}
+
+/// Remove the leading `1.` or `2.3.` numbering from a chapter name, names that are only numbering
+/// or whose number runs into the text like `1.5x faster` are left alone
+fn strip_numbering(chapter_name: &str) -> &str {
+    let mut rest = chapter_name;
+    loop {
+        let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        match rest[digits..].strip_prefix('.') {
+            Some(after) if digits > 0 => rest = after,
+            _ => break,
+        }
+    }
+
+    let stripped = rest.trim_start();
+    if rest.len() == chapter_name.len() || rest.len() == stripped.len() || stripped.is_empty() {
+        chapter_name
+    } else {
+        stripped
+    }
+}
+
+/// The link to a tagged chapter, `root` is prepended so it resolves from wherever it ends up
+fn chapter_href(path: &Path, anchor: Option<&str>, root: &str) -> Result<String> {
+    let path_str = encode_href(
+        &path
+            .to_str()
+            .ok_or_else(|| {
+                ErrorKind::Io(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "Couldn't build output path",
+                ))
+            })?
+            .replace('\\', "/"),
+    );
+
+    Ok(match anchor {
+        Some(anchor) => format!("{}{}#{}", root, path_str, encode_href(anchor)),
+        None => format!("{}{}", root, path_str),
+    })
+}
+
+/// A path as it appears in our links, browsers only follow `/` but windows paths are separated by `\`
+fn link_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+/// Where mdbook renders the chapter at our markdown `path`
+fn html_path(path: &Path) -> String {
+    link_path(&path.with_extension("html"))
+}
+
+/// Our time as an RFC 3339 timestamp in UTC, like `2020-01-31T12:00:00Z`
+fn rfc3339(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let (days, seconds) = (seconds / 86_400, seconds % 86_400);
+
+    // the civil date of our days since the epoch, from http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+/// Find the first chapter named `name` anywhere in our book
+fn find_chapter_mut<'a>(items: &'a mut [BookItem], name: &str) -> Option<&'a mut Chapter> {
+    for item in items.iter_mut() {
+        if let BookItem::Chapter(chapter) = item {
+            if chapter.name == name {
+                return Some(chapter);
+            }
+            if let Some(found) = find_chapter_mut(&mut chapter.sub_items, name) {
+                return Some(found);
+            }
+        }
+    }
+
+    None
+}
+
+/// Whether `path` is the page at `page_path`, or one of the pages nested under it
+fn is_page(path: &Path, page_path: &str, sub_pages: bool) -> bool {
+    let path = strip_current_dir(path);
+
+    path == Path::new(page_path)
+        || (sub_pages && path.starts_with(Path::new(page_path).with_extension("")))
+}
+
+/// Our chapter paths may or may not start with `./`
+fn strip_current_dir(path: &Path) -> &Path {
+    path.strip_prefix(".").unwrap_or(path)
+}
+
+/// mdbook represents chapters that don't have a file yet with an empty path
+fn is_draft(path: &Path) -> bool {
+    path.as_os_str().is_empty()
+}
+
+/// How the tags on our page are grouped
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum GroupBy {
+    None,
+    /// Group tags under a heading for the first letter of their alias
+    Letter,
+}
+
+impl GroupBy {
+    const VALUES: &'static [&'static str] = &["none", "letter"];
+
+    fn from_str(group_by: &str) -> Option<GroupBy> {
+        match group_by {
+            "none" => Some(GroupBy::None),
+            "letter" => Some(GroupBy::Letter),
+            _ => None,
+        }
+    }
+}
+
+/// The order of the tags on our page
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Sort {
+    Alpha,
+    /// The most used tags first
+    Count,
+    /// The tags of the most recently modified chapters first
+    Recent,
+}
+
+impl Sort {
+    const VALUES: &'static [&'static str] = &["alpha", "count", "recent"];
+
+    fn from_str(sort: &str) -> Option<Sort> {
+        match sort {
+            "alpha" => Some(Sort::Alpha),
+            "count" => Some(Sort::Count),
+            "recent" => Some(Sort::Recent),
+            _ => None,
+        }
+    }
+}
+
+/// How our single tags page is split into pages of its own
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Split {
+    None,
+    /// A page for each range of letters, nested under our tags page
+    AlphaRanges,
+}
+
+impl Split {
+    const VALUES: &'static [&'static str] = &["none", "alpha-ranges"];
+
+    fn from_str(split: &str) -> Option<Split> {
+        match split {
+            "none" => Some(Split::None),
+            "alpha-ranges" => Some(Split::AlphaRanges),
+            _ => None,
+        }
+    }
+}
+
+/// Where our tags page goes in the book
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Position {
+    Start,
+    End,
+}
+
+impl Position {
+    const VALUES: &'static [&'static str] = &["start", "end"];
+
+    fn from_str(position: &str) -> Option<Position> {
+        match position {
+            "start" => Some(Position::Start),
+            "end" => Some(Position::End),
+            _ => None,
+        }
+    }
+}
+
+/// The uppercase first letter of an alias, or `None` if it starts with a symbol. Accents are
+/// folded away so `Éclair` is grouped with `eclair`.
+fn letter_group(alias: &str) -> Option<char> {
+    fold(alias)
+        .chars()
+        .next()
+        .filter(|c| c.is_alphabetic())
+        .and_then(|c| c.to_uppercase().next())
+}
+
+/// Strip any accents and lowercase our text so it compares the same regardless of locale
+fn fold(text: &str) -> String {
+    text.nfkd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// The other tags sharing chapters with each of our tags, the ones sharing the most chapters first
+fn related_tags(sorted_tags: &[(String, Vec<Tag>)]) -> HashMap<String, Vec<String>> {
+    let mut chapters: HashMap<&Path, BTreeSet<&str>> = HashMap::new();
+    for (alias, tags) in sorted_tags {
+        for tag in tags.iter().filter(|tag| !is_draft(&tag.path)) {
+            chapters.entry(&tag.path).or_default().insert(alias);
+        }
+    }
+
+    let mut shared: HashMap<&str, HashMap<&str, usize>> = HashMap::new();
+    for aliases in chapters.values() {
+        for alias in aliases {
+            for other in aliases.iter().filter(|other| *other != alias) {
+                *shared.entry(alias).or_default().entry(other).or_default() += 1;
+            }
+        }
+    }
+
+    shared
+        .into_iter()
+        .map(|(alias, counts)| {
+            let mut counts = counts.into_iter().collect::<Vec<_>>();
+            counts.sort_by_cached_key(|(other, count)| (Reverse(*count), collation_key(other)));
+
+            (
+                alias.to_string(),
+                counts
+                    .into_iter()
+                    .map(|(other, _)| other.to_string())
+                    .collect(),
+            )
+        })
+        .collect()
+}
+
+/// Sort an alias by each level of its hierarchy so nested tags stay next to their parents, the
+/// folded text puts `Éclair` next to `eclair` and the raw text keeps our order stable.
+fn collation_key(alias: &str) -> Vec<(String, String)> {
+    alias
+        .split('/')
+        .map(|segment| (fold(segment), segment.to_string()))
+        .collect()
+}
+
+/// Everything that can't be left as is in the path or fragment of a markdown link, a space ends
+/// the link and parentheses can end it early
+const HREF_ENCODE_SET: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'(')
+    .add(b')')
+    .add(b'<')
+    .add(b'>')
+    .add(b'`');
+
+/// Percent-encode a single path or fragment of our links, along with anything outside of ASCII.
+/// Our anchors themselves keep their unicode since browsers decode the fragment before looking
+/// for it.
+fn encode_href(href: &str) -> String {
+    utf8_percent_encode(href, HREF_ENCODE_SET).to_string()
+}
+
+/// The id we use to link to a tag, this is shared by our links and the anchors they point to so
+/// they can't drift apart
+fn slugify(alias: &str) -> String {
+    let slug = alias
+        .chars()
+        .filter_map(|c| {
+            if c.is_alphanumeric() || c == '_' || c == '-' {
+                Some(c.to_lowercase().collect::<String>())
+            } else if c.is_whitespace() || c == '/' || c == ':' {
+                // the levels of our nested tags and our prefixed aliases like `topic:rust`
+                Some("-".into())
+            } else {
+                None
+            }
+        })
+        .collect::<String>();
+
+    // an id can't be empty
+    if slug.is_empty() {
+        "tag".into()
+    } else {
+        slug
+    }
+}
+
+/// Lowercase each level of our alias, joining its words with `-` and dropping any punctuation
+fn slug_alias(alias: &str) -> String {
+    alias
+        .split('/')
+        .map(|segment| {
+            segment
+                .split_whitespace()
+                .map(|word| {
+                    word.chars()
+                        .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_')
+                        .flat_map(char::to_lowercase)
+                        .collect::<String>()
+                })
+                .filter(|word| !word.is_empty())
+                .collect::<Vec<_>>()
+                .join("-")
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Markdown only has 6 levels of headers
+fn push_header<'a>(contents: &mut Vec<md::Event<'a>>, level: i32, name: Vec<md::Event<'a>>) {
+    contents.push(md::Event::Start(md::Tag::Header(level)));
+    contents.extend(name);
+    contents.push(md::Event::End(md::Tag::Header(level)));
+}
+
+/// The anchor for our alias, falling back to its slug if we don't know about it
+fn anchor_id(alias: &str, anchors: &TagAnchors) -> String {
+    anchors
+        .get(alias)
+        .cloned()
+        .unwrap_or_else(|| slugify(alias))
+}
+
+fn push_anchor(contents: &mut Vec<md::Event>, id: &str) {
+    contents.push(md::Event::Start(md::Tag::HtmlBlock));
+    contents.push(md::Event::Html(
+        format!("<a id=\"{}\"></a>\n", escape_html(id)).into(),
+    ));
+    contents.push(md::Event::End(md::Tag::HtmlBlock));
+}
+
+/// Escape the `|` in an event of a table cell, any left bare would end our cell early. A code span
+/// would keep the backslash in front of its `|`, so those become html instead.
+fn escape_cell(event: md::Event) -> md::Event {
+    fn escape(text: &str) -> String {
+        text.replace('|', "\\|")
+    }
+    fn escape_link(link: md::Tag) -> md::Tag {
+        match link {
+            md::Tag::Link(link_type, href, title) => {
+                md::Tag::Link(link_type, escape(&href).into(), escape(&title).into())
+            }
+            tag => tag,
+        }
+    }
+
+    match event {
+        md::Event::Text(text) => md::Event::Text(escape(&text).into()),
+        md::Event::Code(code) if code.contains('|') => md::Event::InlineHtml(
+            format!("<code>{}</code>", escape_html(&code).replace('|', "&#124;")).into(),
+        ),
+        md::Event::Start(link) => md::Event::Start(escape_link(link)),
+        md::Event::End(link) => md::Event::End(escape_link(link)),
+        event => event,
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Replace each `{name}` placeholder in our template with its value in one pass, so our values are
+/// never filled in themselves. Unknown placeholders are left as they are.
+fn fill_template(template: &str, values: &[(&str, String)]) -> String {
+    let mut filled = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        filled.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let value = rest.find('}').and_then(|end| {
+            values
+                .iter()
+                .find(|(name, _)| *name == &rest[1..end])
+                .map(|(_, value)| (end, value))
+        });
+        match value {
+            Some((end, value)) => {
+                filled.push_str(value);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                filled.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    filled.push_str(rest);
+
+    filled
+}
+
+/// Replace each range of our content, our ranges need to be in order and can't overlap
+fn splice(content: &str, replacements: Vec<(Range<usize>, String)>) -> String {
+    let mut buf = String::with_capacity(content.len());
+
+    let mut last = 0;
+    for (range, replacement) in replacements {
+        buf.push_str(&content[last..range.start]);
+        buf.push_str(&replacement);
+        last = range.end;
+    }
+    buf.push_str(&content[last..]);
+
+    buf
+}
+
+/// Which chapter we were working on when something went wrong, large books make this hard to guess
+fn chapter_context(chapter: &Chapter) -> String {
+    format!(
+        "Failed to tag the chapter \"{}\" at {}",
+        chapter.name,
+        chapter.path.display()
+    )
+}
+
+fn to_markdown(events: &[md::Event]) -> Result<String> {
+    let mut buf = String::new();
+
+    cmark(events.iter(), &mut buf, None)
+        .map_err(|err| Error::from(format!("Markdown serialization failed: {}", err)))?;
+
+    Ok(buf)
+}
+
+/// Reject any key we don't know about, they're most likely a typo that would be silently ignored
+fn validate_keys(config: &Table) -> Result<()> {
+    for key in config.keys() {
+        if CONFIG_KEYS.contains(&key.as_str()) {
+            continue;
+        }
+
+        let closest = CONFIG_KEYS
+            .iter()
+            .map(|known| (edit_distance(key, known), known))
+            .filter(|(distance, _)| *distance <= 3)
+            .min();
+        let message = match closest {
+            Some((_, known)) => format!(
+                "Unknown key \"{}\" in [preprocessor.tag], did you mean \"{}\"?",
+                key, known
+            ),
+            None => format!("Unknown key \"{}\" in [preprocessor.tag]", key),
+        };
+
+        return Err(Error::from(message));
+    }
+
+    Ok(())
+}
+
+/// The Levenshtein distance between our strings, how many characters need to change to get from
+/// one to the other
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + if a == *b { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// Merge the tables of our `definitions_file`, relative to our book `root`, into our config and
+/// into each of our namespaces that has its own `definitions_file`. Any entries set inline in a
+/// config win over the ones in its file. `Tagger::new` and `Tagger::from_config` don't read our
+/// `definitions_file`, so their config needs to go through here first.
+pub fn with_definitions(root: &Path, config: Option<&Table>) -> Result<Option<Table>> {
+    fn apply(root: &Path, config: &mut Table) -> Result<()> {
+        let path = match config_str(Some(config), "definitions_file")? {
+            Some(path) => root.join(path),
+            None => return Ok(()),
+        };
+
+        let contents = fs::read_to_string(&path)
+            .chain_err(|| format!("Failed to read the definitions file {}", path.display()))?;
+        let definitions: Table = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&contents).map_err(|err| Error::from(err.to_string()))
+        } else {
+            toml::from_str(&contents).map_err(|err| Error::from(err.to_string()))
+        }
+        .chain_err(|| format!("Failed to parse the definitions file {}", path.display()))?;
+
+        for (key, value) in definitions {
+            if !DEFINITION_KEYS.contains(&key.as_str()) {
+                return Err(Error::from(format!(
+                    "Unknown key \"{}\" in the definitions file {}, expected one of {}",
+                    key,
+                    path.display(),
+                    DEFINITION_KEYS.join(", ")
+                )));
+            }
+            let mut table = value.try_into::<Table>().chain_err(|| {
+                format!(
+                    "Expected {} to be a table in the definitions file {}",
+                    key,
+                    path.display()
+                )
+            })?;
+            if let Some(inline) = config_table(Some(config), &key)? {
+                table.extend(inline.clone());
+            }
+            config.insert(key, Value::Table(table));
+        }
+
+        Ok(())
+    }
+
+    let mut config = match config {
+        Some(config) => config.clone(),
+        None => return Ok(None),
+    };
+    apply(root, &mut config)?;
+    if let Some(Value::Array(namespaces)) = config.get_mut("namespaces") {
+        for namespace in namespaces.iter_mut() {
+            if let Value::Table(namespace) = namespace {
+                apply(root, namespace)?;
+            }
+        }
+    }
+
+    Ok(Some(config))
+}
+
+/// Use the `renderer_paths` entry for our `renderer` as the `filename` of our config and of each of
+/// our namespaces that has its own `renderer_paths`
+pub fn with_renderer_paths(renderer: &str, mut config: Table) -> Table {
+    fn apply(renderer: &str, config: &mut Table) {
+        let filename = config
+            .get("renderer_paths")
+            .and_then(Value::as_table)
+            .and_then(|paths| paths.get(renderer))
+            .and_then(Value::as_str)
+            .map(String::from);
+        if let Some(filename) = filename {
+            config.insert("filename".into(), Value::String(filename));
+        }
+    }
+
+    apply(renderer, &mut config);
+    if let Some(Value::Array(namespaces)) = config.get_mut("namespaces") {
+        for namespace in namespaces.iter_mut() {
+            if let Value::Table(namespace) = namespace {
+                apply(renderer, namespace);
+            }
+        }
+    }
+
+    config
+}
+
+/// The value of `key` in our config once it's been through `convert`, a value that doesn't convert
+/// is an error listing what we `expected` instead
+fn config_value<'a, T, F>(
+    config: Option<&'a Table>,
+    key: &str,
+    expected: &str,
+    convert: F,
+) -> Result<Option<T>>
+where
+    F: FnOnce(&'a Value) -> Option<T>,
+{
+    match config.and_then(|t| t.get(key)) {
+        Some(value) => convert(value)
+            .map(Some)
+            .ok_or_else(|| invalid_value(key, value, expected)),
+        None => Ok(None),
+    }
+}
+
+fn invalid_value(key: &str, value: &Value, expected: &str) -> Error {
+    Error::from(format!(
+        "Expected {} to be {}, found {}",
+        key, expected, value
+    ))
+}
+
+fn config_str<'a>(config: Option<&'a Table>, key: &str) -> Result<Option<&'a str>> {
+    config_value(config, key, "a string", Value::as_str)
+}
+
+fn config_bool(config: Option<&Table>, key: &str) -> Result<Option<bool>> {
+    config_value(config, key, "true or false", Value::as_bool)
+}
+
+/// A whole number of at least `min`
+fn config_count(config: Option<&Table>, key: &str, min: usize) -> Result<Option<usize>> {
+    config_value(config, key, &count_expected(min), |value| {
+        value
+            .as_integer()
+            .and_then(|count| count_at_least(count, min))
+    })
+}
+
+fn count_expected(min: usize) -> String {
+    format!("a whole number of at least {}", min)
+}
+
+fn count_at_least(count: i64, min: usize) -> Option<usize> {
+    usize::try_from(count).ok().filter(|count| *count >= min)
+}
+
+/// One of the `values` our `from_str` understands
+fn config_choice<T>(
+    config: Option<&Table>,
+    key: &str,
+    values: &[&str],
+    from_str: fn(&str) -> Option<T>,
+) -> Result<Option<T>> {
+    let expected = format!(
+        "one of {}",
+        values
+            .iter()
+            .map(|value| format!("\"{}\"", value))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    config_value(config, key, &expected, |value| {
+        value.as_str().and_then(from_str)
+    })
+}
+
+fn config_str_list<'a>(config: Option<&'a Table>, key: &str) -> Result<Vec<&'a str>> {
+    let list = config_value(config, key, "a list of strings", |value| {
+        value
+            .as_array()
+            .and_then(|values| values.iter().map(Value::as_str).collect())
+    })?;
+    Ok(list.unwrap_or_default())
+}
+
+fn config_table<'a>(config: Option<&'a Table>, key: &str) -> Result<Option<&'a Table>> {
+    config_value(config, key, "a table", Value::as_table)
+}
+
+/// The entries of a table of strings, like our `descriptions`
+fn config_str_table<'a>(config: Option<&'a Table>, key: &str) -> Result<Vec<(&'a str, &'a str)>> {
+    let mut entries = vec![];
+    for (entry, value) in config_table(config, key)?.into_iter().flatten() {
+        let string = value
+            .as_str()
+            .ok_or_else(|| invalid_value(&format!("{}.{}", key, entry), value, "a string"))?;
+        entries.push((entry.as_str(), string));
+    }
+
+    Ok(entries)
+}
+
+/// A single occurrence of a tag, along with the alias it's grouped under
+#[derive(Debug, PartialEq, Serialize)]
+pub struct AliasedTag {
+    pub alias: String,
+    pub tag: Tag,
+}
+
+impl AliasedTag {
+    fn new<S: Into<String>>(
+        alias: S,
+        chapter_name: String,
+        path: PathBuf,
+        parent_names: Vec<String>,
+        anchor: Option<String>,
+    ) -> AliasedTag {
+        AliasedTag {
+            alias: alias.into(),
+            tag: Tag {
+                chapter_name,
+                path,
+                parent_names,
+                anchor,
+                snippet: None,
+                number: None,
+            },
+        }
+    }
+}
+
+/// The chapter a tag was found in
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Tag {
+    pub chapter_name: String,
+    pub path: PathBuf,
+    pub parent_names: Vec<String>,
+    /// The id of the anchor placed at this tag's location within the chapter
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anchor: Option<String>,
+    /// The text following this tag, when `snippets` are enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+    /// The section number of our chapter, for `sort_chapters = "book-order"`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub number: Option<SectionNumber>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    static CHAPTER_NAME: &str = "Test Chapter";
+    static CHAPTER_FILE: &str = "chapter.md";
+
+    mod process_chapter {
+        use super::*;
+        use toml::map::Map;
+
+        static EXPECTED: &str = r#"# Chapter
+
+<a id="tag-hello-1"></a>[`#hello`](tags.md#hello "Tag: hello")"#;
+
+        #[test]
+        fn simple_chapter() {
+            let chapter = Chapter::new(
+                CHAPTER_NAME,
+                r#"# Chapter
+
+`tag:hello`"#
+                    .into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+
+            verify_process_chapter(vec!["hello"], chapter, EXPECTED);
+        }
+
+        fn malformed_chapter() -> Chapter {
+            Chapter::new(
+                CHAPTER_NAME,
+                "# Chapter\n\n`tag:` `tag:   ` `tag!:`".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            )
+        }
+
+        #[test]
+        fn malformed_lenient() {
+            let mut chapter = malformed_chapter();
+            let tags = Tagger::new(None)
+                .unwrap()
+                .process_chapter(&mut chapter)
+                .unwrap();
+
+            assert!(tags.is_empty());
+            assert_eq!("# Chapter\n\n`tag:` `tag:   ` `tag!:`", chapter.content);
+        }
+
+        #[test]
+        fn malformed_strict() {
+            let mut config = Map::new();
+            config.insert("strict_syntax".into(), Value::Boolean(true));
+            let tagger = Tagger::new(Some(&config)).unwrap();
+
+            for span in &["`tag:`", "`tag:   `", "`tag!:`", "`tag: , `"] {
+                let mut chapter = Chapter::new(
+                    CHAPTER_NAME,
+                    format!("# Chapter\n\n{}", span),
+                    PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                    vec![],
+                );
+
+                let err = tagger.process_chapter(&mut chapter).unwrap_err();
+                assert!(
+                    err.to_string()
+                        .contains(&format!("without any aliases in ./{}", CHAPTER_FILE)),
+                    "{} should fail, not with {}",
+                    span,
+                    err
+                );
+            }
+        }
+
+        #[test]
+        fn malformed_strict_book() {
+            let mut config = Map::new();
+            config.insert("strict_syntax".into(), Value::Boolean(true));
+            let tagger = Tagger::new(Some(&config)).unwrap();
+
+            let mut book = Book::new();
+            book.push_item(Chapter::new(
+                "Fine",
+                "`tag:hello`".into(),
+                PathBuf::from("./fine.md"),
+                vec![],
+            ));
+            book.push_item(Chapter::new(
+                "Broken",
+                "`tag:`".into(),
+                PathBuf::from("./nested/broken.md"),
+                vec![],
+            ));
+
+            let err = tagger.tag_book(Path::new("."), book).unwrap_err();
+            assert_eq!(
+                "Failed to tag the chapter \"Broken\" at ./nested/broken.md",
+                err.to_string()
+            );
+            assert!(err
+                .iter()
+                .any(|cause| cause.to_string().contains("without any aliases")));
+        }
+
+        fn inline_chapter(inline: &str) -> (Vec<AliasedTag>, Chapter) {
+            let mut config = Map::new();
+            config.insert("inline".into(), Value::String(inline.into()));
+
+            let mut chapter = Chapter::new(
+                CHAPTER_NAME,
+                "# Chapter\n\n`tag:hello`\n\n```tags\nworld\n```\n".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+            let tags = Tagger::new(Some(&config))
+                .unwrap()
+                .process_chapter(&mut chapter)
+                .unwrap();
+
+            (tags, chapter)
+        }
+
+        #[test]
+        fn inline_link() {
+            let (tags, chapter) = inline_chapter("link");
+
+            assert_eq!(
+                vec![Some("tag-hello-1"), Some("tag-world-2")],
+                tags.iter()
+                    .map(|t| t.tag.anchor.as_deref())
+                    .collect::<Vec<_>>()
+            );
+            assert!(chapter
+                .content
+                .contains(r#"<a id="tag-hello-1"></a>[`#hello`](tags.md#hello "Tag: hello")"#));
+        }
+
+        #[test]
+        fn inline_none() {
+            let (tags, chapter) = inline_chapter("none");
+
+            assert_eq!(
+                vec![("hello", None), ("world", None)],
+                tags.iter()
+                    .map(|t| (t.alias.as_str(), t.tag.anchor.as_deref()))
+                    .collect::<Vec<_>>()
+            );
+            assert_eq!("# Chapter\n\n\n\n\n", chapter.content);
+        }
+
+        #[test]
+        fn inline_text() {
+            let (tags, chapter) = inline_chapter("text");
+
+            assert_eq!(
+                vec![
+                    ("hello", Some("tag-hello-1")),
+                    ("world", Some("tag-world-2"))
+                ],
+                tags.iter()
+                    .map(|t| (t.alias.as_str(), t.tag.anchor.as_deref()))
+                    .collect::<Vec<_>>()
+            );
+            assert!(chapter
+                .content
+                .contains(r#"<a id="tag-hello-1"></a>`#hello`"#));
+            assert!(
+                !chapter.content.contains("](tags.md"),
+                "{}",
+                chapter.content
+            );
+        }
+
+        fn alt_text_tags(scan_alt: bool) -> (Vec<String>, String) {
+            let mut config = Map::new();
+            config.insert("scan_alt".into(), Value::Boolean(scan_alt));
+
+            let content =
+                "![A diagram tag:rust `tag:async, io`](diagram.png \"tag:title\") `tag:hello`";
+            let mut chapter = Chapter::new(
+                CHAPTER_NAME,
+                content.into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+
+            let tags = Tagger::new(Some(&config))
+                .unwrap()
+                .process_chapter(&mut chapter)
+                .unwrap();
+
+            (tags.into_iter().map(|t| t.alias).collect(), chapter.content)
+        }
+
+        #[test]
+        fn alt_text_ignored() {
+            let (aliases, content) = alt_text_tags(false);
+
+            assert_eq!(vec!["hello"], aliases);
+            assert_eq!(
+                r#"![A diagram tag:rust `tag:async, io`](diagram.png "tag:title") <a id="tag-hello-1"></a>[`#hello`](tags.md#hello "Tag: hello")"#,
+                content
+            );
+        }
+
+        #[test]
+        fn scan_alt() {
+            let (aliases, content) = alt_text_tags(true);
+
+            assert_eq!(vec!["rust", "async", "io", "hello"], aliases);
+            assert!(content
+                .starts_with(r#"![A diagram tag:rust `tag:async, io`](diagram.png "tag:title") "#));
+        }
+
+        #[test]
+        fn verbose() {
+            let mut config = Map::new();
+            config.insert("verbose".into(), Value::Boolean(true));
+
+            let mut chapter = Chapter::new(
+                CHAPTER_NAME,
+                "`tag:rust, async` `tag:rust`".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+
+            let tags = Tagger::new(Some(&config))
+                .unwrap()
+                .process_chapter(&mut chapter)
+                .unwrap();
+
+            assert_eq!(
+                vec!["rust", "async", "rust"],
+                tags.iter().map(|t| t.alias.as_str()).collect::<Vec<_>>()
+            );
+        }
+
+        #[test]
+        fn chapter_footer() {
+            let mut config = Map::new();
+            config.insert("chapter_footer".into(), Value::Boolean(true));
+
+            let mut chapter = Chapter::new(
+                CHAPTER_NAME,
+                "# Chapter\n\n`tag:rust, async` and `tag!:rust`".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+
+            Tagger::new(Some(&config))
+                .unwrap()
+                .process_chapter(&mut chapter)
+                .unwrap();
+
+            assert!(
+                chapter.content.ends_with(
+                    "\n\nTagged: [rust](tags.md#rust \"Tag: rust\"), \
+                     [async](tags.md#async \"Tag: async\")"
+                ),
+                "{}",
+                chapter.content
+            );
+        }
+
+        #[test]
+        fn embed_metadata() {
+            let mut config = Map::new();
+            config.insert("embed_metadata".into(), Value::Boolean(true));
+
+            let mut chapter = Chapter::new(
+                CHAPTER_NAME,
+                "# Chapter\n\n`tag:Rust, async` and `tag!:rust`".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+
+            Tagger::new(Some(&config))
+                .unwrap()
+                .process_chapter(&mut chapter)
+                .unwrap();
+
+            assert!(
+                chapter
+                    .content
+                    .ends_with("and \n\n<!-- tags: rust, async -->\n"),
+                "{}",
+                chapter.content
+            );
+            let html = mdbook::utils::render_markdown(&chapter.content, false);
+            assert!(html.contains("<!-- tags: rust, async -->"));
+        }
+
+        #[test]
+        fn reference_links() {
+            let mut config = Map::new();
+            config.insert("link_style".into(), Value::String("reference".into()));
+
+            let mut chapter = Chapter::new(
+                CHAPTER_NAME,
+                "# Chapter\n\n`tag:hello` and `tag:world`\n\nAgain `tag:hello`\n".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+
+            Tagger::new(Some(&config))
+                .unwrap()
+                .process_chapter(&mut chapter)
+                .unwrap();
+
+            assert_eq!(
+                r#"# Chapter
+
+<a id="tag-hello-1"></a>[`#hello`][tag:hello] and <a id="tag-world-2"></a>[`#world`][tag:world]
+
+Again <a id="tag-hello-3"></a>[`#hello`][tag:hello]
+
+[tag:hello]: tags.md#hello "Tag: hello"
+[tag:world]: tags.md#world "Tag: world"
+"#,
+                chapter.content
+            );
+
+            // mdbook renders our references the same as inline links
+            let html = mdbook::utils::render_markdown(&chapter.content, false);
+            assert!(html.contains(
+                r#"<a href="tags.html#hello" title="Tag: hello"><code>#hello</code></a>"#
+            ));
+        }
+
+        #[test]
+        fn unicode_alias() {
+            let chapter = Chapter::new(
+                CHAPTER_NAME,
+                "`tag:مرحبا`".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+
+            verify_process_chapter(
+                vec!["مرحبا"],
+                chapter,
+                r#"<a id="tag-مرحبا-1"></a>[`#مرحبا`](tags.md#%D9%85%D8%B1%D8%AD%D8%A8%D8%A7 "Tag: مرحبا")"#,
+            );
+        }
+
+        #[test]
+        fn unicode_case() {
+            let tagger = Tagger::new(None).unwrap();
+
+            // a precomposed `É` and an `E` followed by a combining accent
+            assert_eq!("éclair", tagger.normalize_alias("\u{c9}clair"));
+            assert_eq!("éclair", tagger.normalize_alias("E\u{301}clair"));
+            assert_eq!("straße", tagger.normalize_alias("STRAßE"));
+        }
+
+        #[test]
+        fn tag_block() {
+            let mut chapter = Chapter::new(
+                CHAPTER_NAME,
+                r#"# Chapter
+
+```tags
+hello
+  lang/rust
+
+```
+
+```rust
+let tags = 1;
+```"#
+                    .into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+
+            let tags = Tagger::new(None)
+                .unwrap()
+                .process_chapter(&mut chapter)
+                .unwrap();
+
+            assert_eq!(
+                vec!["hello", "lang/rust"],
+                tags.iter().map(|t| t.alias.as_str()).collect::<Vec<_>>()
+            );
+            assert_eq!(
+                r#"# Chapter
+
+* <a id="tag-hello-1"></a>[`#hello`](tags.md#hello "Tag: hello")
+* <a id="tag-lang-rust-2"></a>[`#lang/rust`](tags.md#lang-rust "Tag: lang/rust")
+
+```rust
+let tags = 1;
+```"#,
+                chapter.content
+            );
+        }
+
+        #[test]
+        fn windows_paths() {
+            let mut chapter = Chapter::new(
+                CHAPTER_NAME,
+                "`tag:hello`".into(),
+                PathBuf::from(r"section\chapter.md"),
+                vec![],
+            );
+
+            Tagger::new(None)
+                .unwrap()
+                .process_chapter(&mut chapter)
+                .unwrap();
+
+            assert_eq!(
+                r#"<a id="tag-hello-1"></a>[`#hello`](../tags.md#hello "Tag: hello")"#,
+                chapter.content
+            );
+        }
+
+        #[test]
+        fn paths_with_spaces() {
+            let mut config = Map::new();
+            config.insert("directory".into(), Value::String("tag index".into()));
+
+            let mut chapter = Chapter::new(
+                CHAPTER_NAME,
+                "`tag:hello`".into(),
+                PathBuf::from("./my section/my chapter.md"),
+                vec![],
+            );
+
+            Tagger::new(Some(&config))
+                .unwrap()
+                .process_chapter(&mut chapter)
+                .unwrap();
+
+            assert_eq!(
+                r#"<a id="tag-hello-1"></a>[`#hello`](../tag%20index/tags.md#hello "Tag: hello")"#,
+                chapter.content
+            );
+        }
+
+        #[test]
+        fn silent_tags() {
+            let mut book = Book::new();
+            book.push_item(Chapter::new(
+                CHAPTER_NAME,
+                "# Chapter\n\nSome prose `tag!:hello, world`".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            ));
+
+            let book = Tagger::new(None)
+                .unwrap()
+                .tag_book(Path::new("."), book)
+                .unwrap();
+
+            match &book.sections[0] {
+                BookItem::Chapter(chapter) => {
+                    assert_eq!("# Chapter\n\nSome prose ", chapter.content)
+                }
+                item => panic!("Expected a chapter, found {:?}", item),
+            }
+            match &book.sections[2] {
+                BookItem::Chapter(chapter) => assert!(chapter.content.contains(&format!(
+                    "## `hello`\n\n* /[{0}](./{1} \"{0}\")",
+                    CHAPTER_NAME, CHAPTER_FILE
+                ))),
+                item => panic!("Expected a chapter, found {:?}", item),
+            }
+        }
+
+        #[test]
+        fn escaped_tags() {
+            let mut chapter = Chapter::new(
+                CHAPTER_NAME,
+                r"Write `\tag:rust` to tag a chapter `tag:hello` `\tag!:quiet` `\other`".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+
+            let tags = Tagger::new(None)
+                .unwrap()
+                .process_chapter(&mut chapter)
+                .unwrap();
+
+            assert_eq!(
+                vec!["hello"],
+                tags.iter().map(|t| t.alias.as_str()).collect::<Vec<_>>()
+            );
+            assert_eq!(
+                r#"Write `tag:rust` to tag a chapter <a id="tag-hello-1"></a>[`#hello`](tags.md#hello "Tag: hello") `tag!:quiet` `\other`"#,
+                chapter.content
+            );
+        }
+
+        #[test]
+        fn snippets() {
+            let mut config = Map::new();
+            config.insert("snippets".into(), Value::Boolean(true));
+            config.insert("snippet_length".into(), Value::Integer(20));
+
+            let mut chapter = Chapter::new(
+                CHAPTER_NAME,
+                r#"`tag:rust, systems` is a *fast* and
+`reliable` language
+
+`tag:short` words `tag:end`"#
+                    .into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+
+            let tags = Tagger::new(Some(&config))
+                .unwrap()
+                .process_chapter(&mut chapter)
+                .unwrap();
+
+            assert_eq!(
+                vec![
+                    ("rust", Some("is a fast and reliab…")),
+                    ("systems", Some("is a fast and reliab…")),
+                    ("short", Some("words")),
+                    ("end", None),
+                ],
+                tags.iter()
+                    .map(|t| (t.alias.as_str(), t.tag.snippet.as_deref()))
+                    .collect::<Vec<_>>()
+            );
+        }
+
+        #[test]
+        fn title_format() {
+            let mut config = Map::new();
+            config.insert(
+                "title_format".into(),
+                Value::String("Etiqueta: {alias}".into()),
+            );
+
+            let mut chapter = Chapter::new(
+                CHAPTER_NAME,
+                "`tag:hello`".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+
+            Tagger::new(Some(&config))
+                .unwrap()
+                .process_chapter(&mut chapter)
+                .unwrap();
+
+            assert_eq!(
+                r#"<a id="tag-hello-1"></a>[`#hello`](tags.md#hello "Etiqueta: hello")"#,
+                chapter.content
+            );
+        }
+
+        #[test]
+        fn invalid_title_format() {
+            let mut config = Map::new();
+            config.insert("title_format".into(), Value::String("Etiqueta".into()));
+
+            let err = Tagger::new(Some(&config)).err().unwrap();
+            assert_eq!(
+                "Invalid title_format \"Etiqueta\": it must contain {alias} to show the tag's name",
+                err.to_string()
+            );
+        }
+
+        #[test]
+        fn transform_content() {
+            let tagger = Tagger::new(None).unwrap();
+
+            let (content, tags) = tagger
+                .transform_content("`tag:hello`", Path::new("section/intro.md"))
+                .unwrap();
+
+            assert_eq!(
+                r#"<a id="tag-hello-1"></a>[`#hello`](../tags.md#hello "Tag: hello")"#,
+                content
+            );
+            assert_eq!("intro", tags[0].tag.chapter_name);
+            assert_eq!(
+                ("`tag:hello`".to_string(), vec![]),
+                tagger
+                    .transform_content("`tag:hello`", Path::new(""))
+                    .unwrap()
+            );
+        }
+
+        #[test]
+        fn case_insensitive() {
+            let mut chapter = Chapter::new(
+                CHAPTER_NAME,
+                "`tag:Hello`".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+
+            let tags = Tagger::new(None)
+                .unwrap()
+                .process_chapter(&mut chapter)
+                .unwrap();
+
+            assert_eq!("hello", tags[0].alias);
+            assert_eq!(
+                r#"<a id="tag-hello-1"></a>[`#Hello`](tags.md#hello "Tag: Hello")"#,
+                chapter.content
+            );
+        }
+
+        /// Our tag links keep the emphasis around them, both with text inside of it and without
+        fn assert_wrapped(wrapper: &str, html_tag: &str) {
+            let mut chapter = Chapter::new(
+                CHAPTER_NAME,
+                format!("{0}see `tag:rust`{0} after {0}`tag:rust`{0}", wrapper),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+            Tagger::new(None)
+                .unwrap()
+                .process_chapter(&mut chapter)
+                .unwrap();
+
+            let link = |i| {
+                format!(
+                    r#"<a id="tag-rust-{}"></a>[`#rust`](tags.md#rust "Tag: rust")"#,
+                    i
+                )
+            };
+            assert_eq!(
+                format!("{0}see {1}{0} after {0}{2}{0}", wrapper, link(1), link(2)),
+                chapter.content
+            );
+
+            let html_link = |i| {
+                format!(
+                    r#"<a id="tag-rust-{}"></a><a href="tags.html#rust" title="Tag: rust"><code>#rust</code></a>"#,
+                    i
+                )
+            };
+            let html = mdbook::utils::render_markdown(&chapter.content, false);
+            assert!(
+                html.contains(&format!(
+                    "<{0}>see {1}</{0}> after <{0}>{2}</{0}>",
+                    html_tag,
+                    html_link(1),
+                    html_link(2)
+                )),
+                "{}",
+                html
+            );
+        }
+
+        #[test]
+        fn emphasis() {
+            assert_wrapped("*", "em");
+            assert_wrapped("_", "em");
+        }
+
+        #[test]
+        fn strong() {
+            assert_wrapped("**", "strong");
+            assert_wrapped("__", "strong");
+        }
+
+        #[test]
+        fn strikethrough() {
+            assert_wrapped("~~", "del");
+        }
+
+        #[test]
+        fn anchor_prefix() {
+            let mut config = Map::new();
+            config.insert("anchor_prefix".into(), Value::String("tag-".into()));
+            let tagger = Tagger::new(Some(&config)).unwrap();
+
+            let mut chapter = Chapter::new(
+                CHAPTER_NAME,
+                "`tag:rust`".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+            let tags = tagger.process_chapter(&mut chapter).unwrap();
+
+            assert_eq!(
+                r#"<a id="tag-rust-1"></a>[`#rust`](tags.md#tag-rust "Tag: rust")"#,
+                chapter.content
+            );
+
+            let page = tagger.build_tags_page(tagger.build_tags_map(tags)).unwrap();
+            assert!(page
+                .content
+                .contains("<a id=\"tag-rust\"></a>\n\n## `rust`"));
+        }
+
+        #[test]
+        fn custom_anchors() {
+            let mut anchors = Map::new();
+            anchors.insert("Rust".into(), Value::String("rust-lang".into()));
+            let mut config = Map::new();
+            config.insert("anchors".into(), Value::Table(anchors));
+            let tagger = Tagger::new(Some(&config)).unwrap();
+
+            let mut chapter = Chapter::new(
+                CHAPTER_NAME,
+                "`tag:rust` `tag:rust-lang`".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+            let tags = tagger.process_chapter(&mut chapter).unwrap();
+
+            assert!(
+                chapter
+                    .content
+                    .contains(r#"[`#rust`](tags.md#rust-lang "Tag: rust")"#),
+                "{}",
+                chapter.content
+            );
+
+            let tags_map = tagger.build_tags_map(tags);
+            let anchors = tagger.tag_anchors(&tags_map);
+            assert_eq!("rust-lang", anchors["rust"]);
+            assert_eq!("rust-lang-2", anchors["rust-lang"]);
+
+            let page = tagger.build_tags_page(tags_map).unwrap();
+            assert!(page
+                .content
+                .contains("<a id=\"rust-lang\"></a>\n\n## `rust`"));
+        }
+
+        #[test]
+        fn case_sensitive() {
+            let mut config = Map::new();
+            config.insert("case_sensitive".into(), Value::Boolean(true));
+
+            let mut chapter = Chapter::new(
+                CHAPTER_NAME,
+                "`tag:Hello` `tag:hello`".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+
+            let tags = Tagger::new(Some(&config))
+                .unwrap()
+                .process_chapter(&mut chapter)
+                .unwrap();
+
+            assert_eq!(
+                vec!["Hello", "hello"],
+                tags.iter().map(|t| t.alias.as_str()).collect::<Vec<_>>()
+            );
+        }
+
+        fn normalized_aliases(normalize: &str) -> Vec<String> {
+            let mut config = Map::new();
+            config.insert("normalize".into(), Value::String(normalize.into()));
+
+            let mut chapter = Chapter::new(
+                CHAPTER_NAME,
+                "`tag:Machine  Learning!` `tag:machine-learning` `tag:Lang/Rust Lang`".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+
+            Tagger::new(Some(&config))
+                .unwrap()
+                .process_chapter(&mut chapter)
+                .unwrap()
+                .into_iter()
+                .map(|t| t.alias)
+                .collect()
+        }
+
+        #[test]
+        fn normalize_lower() {
+            assert_eq!(
+                vec!["machine  learning!", "machine-learning", "lang/rust lang"],
+                normalized_aliases("lower")
+            );
+        }
+
+        #[test]
+        fn normalize_slug() {
+            assert_eq!(
+                vec!["machine-learning", "machine-learning", "lang/rust-lang"],
+                normalized_aliases("slug")
+            );
+        }
+
+        #[test]
+        fn normalize_slug_punctuation() {
+            let mut config = Map::new();
+            config.insert("normalize".into(), Value::String("slug".into()));
+            let tagger = Tagger::new(Some(&config)).unwrap();
+
+            let mut chapter = Chapter::new(
+                CHAPTER_NAME,
+                "`tag:++` `tag:rust, ++` `tag!:++`".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+            let tags = tagger.process_chapter(&mut chapter).unwrap();
+
+            // an alias that's all punctuation has no slug, so it's malformed like an empty tag
+            assert_eq!(
+                vec!["rust"],
+                tags.iter().map(|t| t.alias.as_str()).collect::<Vec<_>>()
+            );
+            assert!(chapter.content.starts_with("`tag:++` "));
+            assert!(chapter.content.ends_with(" `tag!:++`"));
+        }
+
+        fn spaced_aliases(alias_spaces: &str) -> Result<Vec<String>> {
+            let mut config = Map::new();
+            config.insert("alias_spaces".into(), Value::String(alias_spaces.into()));
+
+            let mut chapter = Chapter::new(
+                CHAPTER_NAME,
+                "`tag:Machine  Learning` `tag:rust`".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+
+            let tags = Tagger::new(Some(&config))
+                .unwrap()
+                .process_chapter(&mut chapter)?;
+
+            Ok(tags.into_iter().map(|t| t.alias).collect())
+        }
+
+        #[test]
+        fn alias_spaces_keep() {
+            assert_eq!(
+                vec!["machine  learning", "rust"],
+                spaced_aliases("keep").unwrap()
+            );
+        }
+
+        #[test]
+        fn alias_spaces_hyphenate() {
+            assert_eq!(
+                vec!["machine-learning", "rust"],
+                spaced_aliases("hyphenate").unwrap()
+            );
+        }
+
+        #[test]
+        fn alias_spaces_error() {
+            assert_eq!(
+                format!(
+                    "Found the tag \"machine  learning\" with spaces in ./{}, use dashes instead or \
+                     set alias_spaces to \"keep\" or \"hyphenate\"",
+                    CHAPTER_FILE
+                ),
+                spaced_aliases("error").unwrap_err().to_string()
+            );
+        }
+
+        #[test]
+        fn per_tag_mode() {
+            let mut config = Map::new();
+            config.insert("mode".into(), Value::String("per-tag".into()));
+
+            let mut chapter = Chapter::new(
+                CHAPTER_NAME,
+                "`tag:hello`".into(),
+                PathBuf::from(format!("./subchapter/{}", CHAPTER_FILE)),
+                vec![],
+            );
+
+            Tagger::new(Some(&config))
+                .unwrap()
+                .process_chapter(&mut chapter)
+                .unwrap();
+
+            assert_eq!(
+                r#"<a id="tag-hello-1"></a>[`#hello`](../tags/hello.md "Tag: hello")"#,
+                chapter.content
+            );
+        }
+
+        #[test]
+        fn slugged_link() {
+            let mut chapter = Chapter::new(
+                CHAPTER_NAME,
+                "`tag:Hello World`".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+
+            Tagger::new(None)
+                .unwrap()
+                .process_chapter(&mut chapter)
+                .unwrap();
+
+            assert_eq!(
+                r#"<a id="tag-hello-world-1"></a>[`#Hello World`](tags.md#hello-world "Tag: Hello World")"#,
+                chapter.content
+            );
+        }
+
+        #[test]
+        fn synonyms() {
+            let mut synonyms = Map::new();
+            synonyms.insert("JS".into(), Value::String("JavaScript".into()));
+            let mut config = Map::new();
+            config.insert("synonyms".into(), Value::Table(synonyms));
+
+            let mut chapter = Chapter::new(
+                CHAPTER_NAME,
+                "`tag:js` `tag:javascript`".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+
+            let tags = Tagger::new(Some(&config))
+                .unwrap()
+                .process_chapter(&mut chapter)
+                .unwrap();
+
+            assert_eq!(
+                vec!["javascript", "javascript"],
+                tags.iter().map(|t| t.alias.as_str()).collect::<Vec<_>>()
+            );
+            assert_eq!(
+                r#"<a id="tag-js-1"></a>[`#js`](tags.md#javascript "Tag: js") <a id="tag-javascript-2"></a>[`#javascript`](tags.md#javascript "Tag: javascript")"#,
+                chapter.content
+            );
+        }
+
+        #[test]
+        fn display() {
+            for (display, expected) in &[("hash", "#hello"), ("name", "hello"), ("both", "hello #")]
+            {
+                let mut config = Map::new();
+                config.insert("display".into(), Value::String(display.to_string()));
+
+                let mut chapter = Chapter::new(
+                    CHAPTER_NAME,
+                    "`tag:hello`".into(),
+                    PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                    vec![],
+                );
+                Tagger::new(Some(&config))
+                    .unwrap()
+                    .process_chapter(&mut chapter)
+                    .unwrap();
+
+                assert_eq!(
+                    format!(
+                        r#"<a id="tag-hello-1"></a>[`{}`](tags.md#hello "Tag: hello")"#,
+                        expected
+                    ),
+                    chapter.content
+                );
+            }
+        }
+
+        #[test]
+        fn custom_prefix() {
+            let mut config = Map::new();
+            config.insert("prefix".into(), Value::String("@".into()));
+
+            let mut chapter = Chapter::new(
+                CHAPTER_NAME,
+                "`@hello` `tag:world` `@`".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+
+            let tags = Tagger::new(Some(&config))
+                .unwrap()
+                .process_chapter(&mut chapter)
+                .unwrap();
+
+            assert_eq!(1, tags.len());
+            assert_eq!("hello", tags[0].alias);
+            assert_eq!(
+                r#"<a id="tag-hello-1"></a>[`#hello`](tags.md#hello "Tag: hello") `tag:world` `@`"#,
+                chapter.content
+            );
+        }
+
+        #[test]
+        fn frontmatter() {
+            let mut chapter = Chapter::new(
+                CHAPTER_NAME,
+                r#"---
+tags: [Rust, async]
+---
+# Chapter
+
+`tag:hello`"#
+                    .into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+
+            let tags = Tagger::new(None)
+                .unwrap()
+                .process_chapter(&mut chapter)
+                .unwrap();
+
+            assert_eq!(
+                vec![
+                    ("rust", None),
+                    ("async", None),
+                    ("hello", Some("tag-hello-3".to_string()))
+                ],
+                tags.iter()
+                    .map(|t| (t.alias.as_str(), t.tag.anchor.clone()))
+                    .collect::<Vec<_>>()
+            );
+            assert_eq!(
+                r#"# Chapter
+
+<a id="tag-hello-3"></a>[`#hello`](tags.md#hello "Tag: hello")"#,
+                chapter.content
+            );
+        }
+
+        #[test]
+        fn sub_dir_chapter() {
+            let chapter = Chapter::new(
+                CHAPTER_NAME,
+                r#"# Chapter
+
+`tag:hello`"#
+                    .into(),
+                PathBuf::from(format!("./subchapter/{}", CHAPTER_FILE)),
+                vec![],
+            );
+
+            verify_process_chapter(
+                vec!["hello"],
+                chapter,
+                r#"# Chapter
+
+<a id="tag-hello-1"></a>[`#hello`](../tags.md#hello "Tag: hello")"#,
+            );
+        }
+
+        #[test]
+        fn sub_chapter() {
+            let chapter = Chapter::new(
+                CHAPTER_NAME,
+                r#"# Chapter
+
+`tag:hello`"#
+                    .into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec!["Parent One".into(), "Parent Two".into()],
+            );
+
+            verify_process_chapter(vec!["hello"], chapter, EXPECTED);
+        }
+
+        #[test]
+        fn multiple_tags() {
+            let chapter = Chapter::new(
+                CHAPTER_NAME,
+                r#"# Chapter
+
+`tag:hello, world,,`"#
+                    .into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+
+            verify_process_chapter(
+                vec!["hello", "world"],
+                chapter,
+                r#"# Chapter
+
+<a id="tag-hello-1"></a>[`#hello`](tags.md#hello "Tag: hello") <a id="tag-world-2"></a>[`#world`](tags.md#world "Tag: world")"#,
+            );
+        }
+
+        #[test]
+        fn round_trip() {
+            let content = r#"# Chapter
+
+* a list
+- with *odd* __formatting__
+1) and   `code`
+
+foo`tag:hello`bar *see `tag:world`*
+"#;
+            let mut chapter = Chapter::new(
+                CHAPTER_NAME,
+                content.into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+
+            Tagger::new(None)
+                .unwrap()
+                .process_chapter(&mut chapter)
+                .unwrap();
+
+            assert_eq!(
+                r#"# Chapter
+
+* a list
+- with *odd* __formatting__
+1) and   `code`
+
+foo<a id="tag-hello-1"></a>[`#hello`](tags.md#hello "Tag: hello")bar *see <a id="tag-world-2"></a>[`#world`](tags.md#world "Tag: world")*
+"#,
+                chapter.content
+            );
+        }
+
+        #[test]
+        fn draft_chapter() {
+            let content = "`tag:hello`";
+            let mut chapter = Chapter::new(CHAPTER_NAME, content.into(), PathBuf::new(), vec![]);
+
+            let tags = Tagger::new(None)
+                .unwrap()
+                .process_chapter(&mut chapter)
+                .unwrap();
+
+            assert!(tags.is_empty());
+            assert_eq!(content, chapter.content);
+        }
+
+        #[test]
+        fn excluded_chapter() {
+            let mut config = Map::new();
+            config.insert(
+                "exclude".into(),
+                Value::Array(vec![Value::String("appendix/**".into())]),
+            );
+            let tagger = Tagger::new(Some(&config)).unwrap();
+
+            let content = "`tag:hello`";
+            let mut chapter = Chapter::new(
+                CHAPTER_NAME,
+                content.into(),
+                PathBuf::from(format!("./appendix/generated/{}", CHAPTER_FILE)),
+                vec![],
+            );
+            assert!(tagger.process_chapter(&mut chapter).unwrap().is_empty());
+            assert_eq!(content, chapter.content);
+
+            let mut chapter = Chapter::new(
+                CHAPTER_NAME,
+                content.into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+            assert_eq!(1, tagger.process_chapter(&mut chapter).unwrap().len());
+        }
+
+        #[test]
+        fn invalid_exclude() {
+            let mut config = Map::new();
+            config.insert(
+                "exclude".into(),
+                Value::Array(vec![Value::String("[".into())]),
+            );
+
+            assert!(Tagger::new(Some(&config)).is_err());
+        }
+
+        fn verify_process_chapter(tag_aliases: Vec<&str>, mut chapter: Chapter, expected: &str) {
+            let tagger = Tagger::new(None).unwrap();
+            let tags: Vec<_> = tag_aliases
+                .into_iter()
+                .enumerate()
+                .map(|(i, alias)| {
+                    AliasedTag::new(
+                        alias,
+                        chapter.name.clone(),
+                        chapter.path.clone(),
+                        chapter.parent_names.clone(),
+                        Some(format!("tag-{}-{}", alias, i + 1)),
+                    )
+                })
+                .collect();
+
+            assert_eq!(tags, tagger.process_chapter(&mut chapter).unwrap());
+
+            assert_eq!(expected, chapter.content);
+        }
+    }
+
+    mod slugify {
+        use super::*;
+
+        #[test]
+        fn slugs() {
+            assert_eq!("hello", slugify("hello"));
+            assert_eq!("hello-world", slugify("Hello World"));
+            assert_eq!("lang-rust", slugify("lang/rust"));
+            assert_eq!("c", slugify("C++"));
+            assert_eq!("tag", slugify("++"));
+        }
+
+        #[test]
+        fn unicode() {
+            assert_eq!("日本語", slugify("日本語"));
+            assert_eq!("مرحبا-بالعالم", slugify("مرحبا بالعالم"));
+            assert_eq!("éclair", slugify("Éclair"));
+        }
+    }
+
+    mod tag_anchors {
+        use super::*;
+
+        fn colliding_tags() -> HashMap<String, Vec<Tag>> {
+            let chapter_tag = Tag {
+                chapter_name: "Chapter".into(),
+                path: PathBuf::from("./chapter.md"),
+                parent_names: vec![],
+                anchor: None,
+                snippet: None,
+                number: None,
+            };
+
+            let mut tags: HashMap<String, _> = HashMap::new();
+            for alias in &["c", "c++", "c#"] {
+                tags.insert(alias.to_string(), vec![chapter_tag.clone()]);
+            }
+
+            tags
+        }
+
+        #[test]
+        fn collisions() {
+            let tagger = Tagger::new(None).unwrap();
+
+            let anchors = tagger.tag_anchors(&colliding_tags());
+
+            assert_eq!("c", anchors["c"]);
+            assert_eq!("c-2", anchors["c#"]);
+            assert_eq!("c-3", anchors["c++"]);
+        }
+
+        #[test]
+        fn reserved_top() {
+            let mut tags = colliding_tags();
+            tags.insert("top".into(), tags["c"].clone());
+
+            let anchors = TaggerBuilder::new()
+                .back_to_top(true)
+                .build()
+                .unwrap()
+                .tag_anchors(&tags);
+            assert_eq!("top-2", anchors["top"]);
+
+            // with a prefix our tag's id is `tag-top`, so it can keep its slug
+            let anchors = TaggerBuilder::new()
+                .back_to_top(true)
+                .anchor_prefix("tag-")
+                .build()
+                .unwrap()
+                .tag_anchors(&tags);
+            assert_eq!("top", anchors["top"]);
+        }
+
+        #[test]
+        fn links_match_page() {
+            let tagger = Tagger::new(None).unwrap();
+            let tags = colliding_tags();
+            let anchors = tagger.tag_anchors(&tags);
+
+            let mut chapter = Chapter::new(
+                CHAPTER_NAME,
+                "`tag:C++`".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+            tagger.link_chapter(&mut chapter, &anchors).unwrap();
+            assert_eq!(
+                r#"<a id="tag-c-1"></a>[`#C++`](tags.md#c-3 "Tag: C++")"#,
+                chapter.content
+            );
+
+            let page = tagger.build_tags_page(tags).unwrap();
+            assert!(page.content.contains("<a id=\"c-3\"></a>\n\n## `c++`"));
+            assert!(page.content.contains("<a id=\"c\"></a>\n\n## `c`"));
+        }
+    }
+
+    mod collect_tags {
+        use super::*;
+
+        #[test]
+        fn unmodified_book() {
+            let content = "`tag:hello`";
+            let mut book = Book::new();
+            book.push_item(Chapter::new(
+                CHAPTER_NAME,
+                content.into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            ));
+
+            let tags = Tagger::new(None).unwrap().collect_tags(&book).unwrap();
+
+            assert_eq!(vec!["hello"], tags.keys().collect::<Vec<_>>());
+            assert_eq!(CHAPTER_NAME, tags["hello"][0].chapter_name);
+            match &book.sections[0] {
+                BookItem::Chapter(chapter) => assert_eq!(content, chapter.content),
+                item => panic!("Expected a chapter, found {:?}", item),
+            }
+        }
+    }
+
+    mod merge_tag_maps {
+        use super::*;
+
+        fn tag(chapter_name: &str) -> Tag {
+            Tag {
+                chapter_name: chapter_name.into(),
+                path: PathBuf::from(format!("{}.md", chapter_name)),
+                parent_names: vec![],
+                anchor: None,
+                snippet: None,
+                number: None,
+            }
+        }
+
+        #[test]
+        fn overlapping_aliases() {
+            let mut first: HashMap<String, _> = HashMap::new();
+            first.insert("rust".into(), vec![tag("a")]);
+            first.insert("async".into(), vec![tag("a")]);
+            let mut second: HashMap<String, _> = HashMap::new();
+            second.insert("rust".into(), vec![tag("b"), tag("c")]);
+            second.insert("wasm".into(), vec![tag("c")]);
+
+            let merged = Tagger::merge_tag_maps(vec![first, second]);
+
+            assert_eq!(3, merged.len());
+            assert_eq!(vec![tag("a"), tag("b"), tag("c")], merged["rust"]);
+            assert_eq!(vec![tag("a")], merged["async"]);
+            assert_eq!(vec![tag("c")], merged["wasm"]);
+        }
+
+        #[test]
+        fn read_json() {
+            let tagger = Tagger::new(None).unwrap();
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert(
+                "rust".into(),
+                vec![Tag {
+                    anchor: Some("tag-rust-1".into()),
+                    number: Some(SectionNumber(vec![1, 2])),
+                    ..tag("a")
+                }],
+            );
+            let mut json = vec![];
+            tagger.write_json(&tags, &mut json).unwrap();
+
+            let ours: HashMap<String, _> = [("rust".to_string(), vec![tag("b")])].into();
+            let theirs = Tagger::read_json(json.as_slice(), Path::new("../other/src")).unwrap();
+            let merged = Tagger::merge_tag_maps(vec![ours, theirs]);
+
+            let mut page = vec![];
+            tagger.write_tags_page(merged, &mut page).unwrap();
+            let page = String::from_utf8(page).unwrap();
+
+            assert!(
+                page.contains("* /[a](../other/src/a.md#tag-rust-1 \"a\")"),
+                "{}",
+                page
+            );
+            assert!(page.contains("* /[b](b.md \"b\")"), "{}", page);
+        }
+
+        #[test]
+        fn read_json_drafts() {
+            let json = r#"{
+  "rust": [
+    {"chapter_name": "a", "path": "./a.md", "parent_names": []},
+    {"chapter_name": "Draft", "path": "", "parent_names": []}
+  ],
+  "draft": [{"chapter_name": "Draft", "path": "", "parent_names": []}]
+}"#;
+
+            let theirs = Tagger::read_json(json.as_bytes(), Path::new("../other/src")).unwrap();
+
+            assert_eq!(vec!["rust"], theirs.keys().collect::<Vec<_>>());
+            assert_eq!(
+                vec![PathBuf::from("../other/src/a.md")],
+                theirs["rust"]
+                    .iter()
+                    .map(|t| t.path.clone())
+                    .collect::<Vec<_>>()
+            );
+        }
+    }
+
+    mod with_renderer_paths {
+        use super::*;
+        use toml::map::Map;
+
+        #[test]
+        fn renderer_override() {
+            let mut paths = Map::new();
+            paths.insert("markdown".into(), Value::String("tags.markdown".into()));
+            let mut config = Map::new();
+            config.insert("filename".into(), Value::String("topics.md".into()));
+            config.insert("renderer_paths".into(), Value::Table(paths));
+
+            let page_path = |renderer: &str| {
+                let config = with_renderer_paths(renderer, config.clone());
+                Tagger::new(Some(&config)).unwrap().page_path()
+            };
+
+            assert_eq!("tags.markdown", page_path("markdown"));
+            assert_eq!("topics.md", page_path("html"));
+        }
+
+        #[test]
+        fn namespaces() {
+            let namespace = |prefix: &str, filename: &str| {
+                let mut namespace = Map::new();
+                namespace.insert("prefix".into(), Value::String(prefix.into()));
+                namespace.insert("filename".into(), Value::String(filename.into()));
+                namespace
+            };
+            let mut paths = Map::new();
+            paths.insert(
+                "markdown".into(),
+                Value::String("audiences.markdown".into()),
+            );
+            let mut audiences = namespace("audience:", "audiences.md");
+            audiences.insert("renderer_paths".into(), Value::Table(paths));
+            let mut config = Map::new();
+            config.insert(
+                "namespaces".into(),
+                Value::Array(vec![
+                    Value::Table(namespace("topic:", "topics.md")),
+                    Value::Table(audiences),
+                ]),
+            );
+
+            let config = with_renderer_paths("markdown", config);
+            let pages = Tagger::namespaces(Some(&config))
+                .unwrap()
+                .iter()
+                .map(Tagger::page_path)
+                .collect::<Vec<_>>();
+
+            assert_eq!(vec!["topics.md", "audiences.markdown"], pages);
+        }
+    }
+
+    mod with_definitions {
+        use super::*;
+        use toml::map::Map;
+
+        fn tags_page(filename: &str, definitions: &str, config: &mut Table) -> String {
+            let root = std::env::temp_dir().join(format!(
+                "mdbook-tag-definitions-{}-{}",
+                filename,
+                std::process::id()
+            ));
+            fs::create_dir_all(&root).unwrap();
+            fs::write(root.join(filename), definitions).unwrap();
+
+            config.insert("definitions_file".into(), Value::String(filename.into()));
+            let config = with_definitions(&root, Some(config));
+            fs::remove_dir_all(&root).unwrap();
+            let tagger = Tagger::new(config.unwrap().as_ref()).unwrap();
+
+            let mut book = Book::new();
+            book.push_item(Chapter::new(
+                "Chapter",
+                "`tag:js` `tag:rust`".into(),
+                PathBuf::from("chapter.md"),
+                vec![],
+            ));
+            match tagger
+                .tag_book(Path::new("."), book)
+                .unwrap()
+                .sections
+                .pop()
+            {
+                Some(BookItem::Chapter(chapter)) => chapter.content,
+                other => panic!("Expected our tags page, found {:?}", other),
+            }
+        }
+
+        #[test]
+        fn toml() {
+            let mut descriptions = Map::new();
+            descriptions.insert("rust".into(), Value::String("Inline *Rust*".into()));
+            let mut config = Map::new();
+            config.insert("descriptions".into(), Value::Table(descriptions));
+
+            let content = tags_page(
+                "tags.toml",
+                r#"
+[descriptions]
+javascript = "The *JavaScript* language"
+rust = "From the file"
+
+[synonyms]
+js = "javascript"
+"#,
+                &mut config,
+            );
+
+            assert!(content.contains("## `javascript`\n\nThe *JavaScript* language"));
+            assert!(content.contains("## `rust`\n\nInline *Rust*"));
+            assert!(!content.contains("From the file"));
+        }
+
+        #[test]
+        fn json() {
+            let content = tags_page(
+                "tags.json",
+                r#"{"descriptions": {"rust": "The *Rust* language"}}"#,
+                &mut Map::new(),
+            );
+
+            assert!(content.contains("## `rust`\n\nThe *Rust* language"));
+        }
+
+        #[test]
+        fn unknown_key() {
+            let root = std::env::temp_dir().join(format!(
+                "mdbook-tag-definitions-unknown-{}",
+                std::process::id()
+            ));
+            fs::create_dir_all(&root).unwrap();
+            fs::write(root.join("tags.toml"), "[titles]\nrust = \"Rust\"\n").unwrap();
+
+            let mut config = Map::new();
+            config.insert("definitions_file".into(), Value::String("tags.toml".into()));
+            let err = with_definitions(&root, Some(&config)).unwrap_err();
+            fs::remove_dir_all(&root).unwrap();
+
+            assert!(
+                err.to_string().starts_with("Unknown key \"titles\""),
+                "{}",
+                err
+            );
+        }
+
+        #[test]
+        fn namespaces() {
+            let root = std::env::temp_dir().join(format!(
+                "mdbook-tag-definitions-namespaces-{}",
+                std::process::id()
+            ));
+            fs::create_dir_all(&root).unwrap();
+            fs::write(
+                root.join("topics.toml"),
+                "[synonyms]\njs = \"javascript\"\n",
+            )
+            .unwrap();
+
+            let mut topics = Map::new();
+            topics.insert("prefix".into(), Value::String("topic:".into()));
+            topics.insert("filename".into(), Value::String("topics.md".into()));
+            topics.insert(
+                "definitions_file".into(),
+                Value::String("topics.toml".into()),
+            );
+            let mut config = Map::new();
+            config.insert(
+                "namespaces".into(),
+                Value::Array(vec![Value::Table(topics)]),
+            );
+            let config = with_definitions(&root, Some(&config)).unwrap();
+            fs::remove_dir_all(&root).unwrap();
+
+            let mut book = Book::new();
+            book.push_item(Chapter::new(
+                "Chapter",
+                "`topic:js`".into(),
+                PathBuf::from("chapter.md"),
+                vec![],
+            ));
+            let mut book = Tagger::namespaces(config.as_ref())
+                .unwrap()
+                .remove(0)
+                .tag_book(Path::new("."), book)
+                .unwrap();
+
+            match book.sections.pop() {
+                Some(BookItem::Chapter(chapter)) => {
+                    assert!(chapter.content.contains("## `javascript`"))
+                }
+                other => panic!("Expected our topics page, found {:?}", other),
+            }
+        }
+    }
+
+    mod validate_keys {
+        use super::*;
+        use toml::map::Map;
+
+        fn error(key: &str) -> String {
+            let mut config = Map::new();
+            config.insert(key.into(), Value::String("tags.md".into()));
+
+            match Tagger::new(Some(&config)) {
+                Ok(_) => panic!("Expected {} to be rejected", key),
+                Err(e) => e.to_string(),
+            }
+        }
+
+        #[test]
+        fn typo() {
+            assert_eq!(
+                "Unknown key \"filname\" in [preprocessor.tag], did you mean \"filename\"?",
+                error("filname")
+            );
+        }
+
+        #[test]
+        fn unknown() {
+            assert_eq!(
+                "Unknown key \"xyzzy\" in [preprocessor.tag]",
+                error("xyzzy")
+            );
+        }
+
+        #[test]
+        fn namespace_typo() {
+            let mut namespace = Map::new();
+            namespace.insert("prefx".into(), Value::String("topic:".into()));
+            let mut config = Map::new();
+            config.insert(
+                "namespaces".into(),
+                Value::Array(vec![Value::Table(namespace)]),
+            );
+
+            assert!(Tagger::namespaces(Some(&config)).is_err());
+        }
+
+        #[test]
+        fn readme_config() {
+            let readme = include_str!("../Readme.md");
+            let start = readme.find("```toml\n").unwrap() + "```toml\n".len();
+            let end = start + readme[start..].find("```").unwrap();
+            let book: Value = toml::from_str(&readme[start..end]).unwrap();
+
+            let config = book["preprocessor"]["tag"].as_table().unwrap();
+            assert!(Tagger::namespaces(Some(config)).is_ok());
+        }
+
+        #[test]
+        fn edit_distance() {
+            assert_eq!(0, super::edit_distance("filename", "filename"));
+            assert_eq!(1, super::edit_distance("filname", "filename"));
+            assert_eq!(2, super::edit_distance("sort", "sotr"));
+            assert_eq!(3, super::edit_distance("", "abc"));
+        }
+    }
+
+    mod invalid_values {
+        use super::*;
+        use toml::map::Map;
+
+        fn error(key: &str, value: Value) -> String {
+            let mut config = Map::new();
+            config.insert(key.into(), value);
+
+            match Tagger::new(Some(&config)) {
+                Ok(_) => panic!("Expected {} to be rejected", key),
+                Err(e) => e.to_string(),
+            }
+        }
+
+        #[test]
+        fn choice() {
+            assert_eq!(
+                "Expected layout to be one of \"list\", \"table\", \"glossary\", \"deflist\", \
+                 found \"tabel\"",
+                error("layout", Value::String("tabel".into()))
+            );
+        }
+
+        #[test]
+        fn boolean() {
+            assert_eq!(
+                "Expected show_counts to be true or false, found \"yes\"",
+                error("show_counts", Value::String("yes".into()))
+            );
+        }
+
+        #[test]
+        fn count() {
+            assert_eq!(
+                "Expected min_count to be a whole number of at least 1, found -1",
+                error("min_count", Value::Integer(-1))
+            );
+            assert_eq!(
+                "Expected heading_offset to be a whole number of at least 0, found \"2\"",
+                error("heading_offset", Value::String("2".into()))
+            );
+        }
+
+        #[test]
+        fn string() {
+            assert_eq!(
+                "Expected title to be a string, found 1",
+                error("title", Value::Integer(1))
+            );
+        }
+
+        #[test]
+        fn table_entry() {
+            let mut descriptions = Map::new();
+            descriptions.insert("rust".into(), Value::Boolean(true));
+
+            assert_eq!(
+                "Expected descriptions.rust to be a string, found true",
+                error("descriptions", Value::Table(descriptions))
+            );
+        }
+    }
+
+    mod draft_parent {
+        use super::*;
+        use toml::map::Map;
+
+        #[test]
+        fn link_to_parent() {
+            let mut config = Map::new();
+            config.insert("draft_parent".into(), Value::Boolean(true));
+
+            let mut draft = Chapter::new("Draft", "`tag:hello`".into(), PathBuf::new(), vec![]);
+            draft.sub_items.push(BookItem::Chapter(Chapter::new(
+                "Nested Draft",
+                "`tag:world`".into(),
+                PathBuf::new(),
+                vec![],
+            )));
+            let mut parent = Chapter::new(
+                CHAPTER_NAME,
+                "# Parent".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+            parent.sub_items.push(BookItem::Chapter(draft));
+
+            let mut book = Book::new();
+            book.push_item(Chapter::new(
+                "Orphan",
+                "`tag:orphan`".into(),
+                PathBuf::new(),
+                vec![],
+            ));
+            book.push_item(parent);
+
+            let tags = Tagger::new(Some(&config))
+                .unwrap()
+                .collect_tags(&book)
+                .unwrap();
+
+            let mut aliases = tags.keys().collect::<Vec<_>>();
+            aliases.sort();
+            assert_eq!(vec!["hello", "world"], aliases);
+            assert_eq!(
+                vec![Tag {
+                    chapter_name: "Draft".into(),
+                    path: PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                    parent_names: vec![],
+                    anchor: None,
+                    snippet: None,
+                    number: None,
+                }],
+                tags["hello"]
+            );
+            assert_eq!(
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                tags["world"][0].path
+            );
+
+            let page = Tagger::new(Some(&config))
+                .unwrap()
+                .build_tags_page(tags)
+                .unwrap();
+            assert!(page.content.contains("[Draft](./chapter.md \"Draft\")"));
+        }
+
+        #[test]
+        fn skipped_by_default() {
+            let mut parent = Chapter::new(
+                CHAPTER_NAME,
+                "# Parent".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+            parent.sub_items.push(BookItem::Chapter(Chapter::new(
+                "Draft",
+                "`tag:hello`".into(),
+                PathBuf::new(),
+                vec![],
+            )));
+            let mut book = Book::new();
+            book.push_item(parent);
+
+            assert!(Tagger::new(None)
+                .unwrap()
+                .collect_tags(&book)
+                .unwrap()
+                .is_empty());
+        }
+    }
+
+    mod untagged_chapters {
+        use super::*;
+
+        #[test]
+        fn sorted() {
+            let mut book = Book::new();
+            for (name, content) in [("c", "# C"), ("b", "`tag:hello`"), ("a", "# A")] {
+                book.push_item(Chapter::new(
+                    name,
+                    content.into(),
+                    PathBuf::from(format!("./{}.md", name)),
+                    vec![],
+                ));
+            }
+            book.push_item(Chapter::new("draft", "".into(), PathBuf::new(), vec![]));
+
+            let untagged = Tagger::new(None).unwrap().untagged_chapters(&book).unwrap();
+
+            assert_eq!(
+                vec![PathBuf::from("./a.md"), PathBuf::from("./c.md")],
+                untagged
+            );
+        }
+    }
+
+    mod ignore {
+        use super::*;
+        use toml::map::Map;
+
+        fn ignore_config(ignore_inline: Option<&str>) -> Table {
+            let mut config = Map::new();
+            config.insert(
+                "ignore".into(),
+                Value::Array(vec![Value::String("Internal".into())]),
+            );
+            if let Some(ignore_inline) = ignore_inline {
+                config.insert("ignore_inline".into(), Value::String(ignore_inline.into()));
+            }
+
+            config
+        }
+
+        fn book() -> Book {
+            let mut book = Book::new();
+            book.push_item(Chapter::new(
+                CHAPTER_NAME,
+                "`tag:hello, internal` `tag:internal`".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            ));
+
+            book
+        }
+
+        #[test]
+        fn no_header() {
+            let tagger = Tagger::new(Some(&ignore_config(None))).unwrap();
+
+            let tags = tagger.collect_tags(&book()).unwrap();
+            let page = tagger.build_tags_page(tags).unwrap();
+
+            assert!(page.content.contains("## `hello`"));
+            assert!(!page.content.contains("internal"));
+        }
+
+        #[test]
+        fn inline_text() {
+            let tagger = Tagger::new(Some(&ignore_config(None))).unwrap();
+
+            let mut book = book();
+            match &mut book.sections[0] {
+                BookItem::Chapter(chapter) => {
+                    tagger.process_chapter(chapter).unwrap();
+                    assert_eq!(
+                        r#"<a id="tag-hello-1"></a>[`#hello`](tags.md#hello "Tag: hello") `#internal` `#internal`"#,
+                        chapter.content
+                    );
+                }
+                item => panic!("Expected a chapter, found {:?}", item),
+            }
+        }
+
+        #[test]
+        fn inline_remove() {
+            let tagger = Tagger::new(Some(&ignore_config(Some("remove")))).unwrap();
+
+            let mut book = book();
+            match &mut book.sections[0] {
+                BookItem::Chapter(chapter) => {
+                    tagger.process_chapter(chapter).unwrap();
+                    assert_eq!(
+                        r#"<a id="tag-hello-1"></a>[`#hello`](tags.md#hello "Tag: hello") "#,
+                        chapter.content
+                    );
+                }
+                item => panic!("Expected a chapter, found {:?}", item),
+            }
+        }
+    }
+
+    mod build_tags_map {
+        use super::*;
+        use toml::map::Map;
+
+        fn aliased_tag(alias: &str, chapter_name: &str, anchor: &str) -> AliasedTag {
+            AliasedTag::new(
+                alias,
+                chapter_name.into(),
+                PathBuf::from(format!("./{}.md", chapter_name)),
+                vec![],
+                Some(anchor.into()),
+            )
+        }
+
+        #[test]
+        fn dedupe() {
+            let tagger = Tagger::new(None).unwrap();
+
+            let tags_map = tagger.build_tags_map(vec![
+                aliased_tag("hello", "a", "tag-hello-1"),
+                aliased_tag("hello", "a", "tag-hello-2"),
+                aliased_tag("hello", "b", "tag-hello-1"),
+            ]);
+
+            let chapters = tags_map["hello"]
+                .iter()
+                .map(|t| t.anchor.clone().unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(vec!["tag-hello-1", "tag-hello-1"], chapters);
+            assert_eq!(PathBuf::from("./b.md"), tags_map["hello"][1].path);
+        }
+
+        #[test]
+        fn no_dedupe() {
+            let mut config = Map::new();
+            config.insert("dedupe".into(), Value::Boolean(false));
+
+            let tagger = Tagger::new(Some(&config)).unwrap();
+
+            let tags_map = tagger.build_tags_map(vec![
+                aliased_tag("hello", "a", "tag-hello-1"),
+                aliased_tag("hello", "a", "tag-hello-2"),
+            ]);
+
+            assert_eq!(2, tags_map["hello"].len());
+        }
+    }
+
+    mod check_singletons {
+        use super::*;
+        use toml::map::Map;
+
+        fn tags() -> HashMap<String, Vec<Tag>> {
+            let tag = |chapter_name: &str| Tag {
+                chapter_name: chapter_name.into(),
+                path: PathBuf::from(format!("./{}.md", chapter_name)),
+                parent_names: vec![],
+                anchor: None,
+                snippet: None,
+                number: None,
+            };
+
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert("once".into(), vec![tag("a")]);
+            tags.insert("twice".into(), vec![tag("a"), tag("b")]);
+
+            tags
+        }
+
+        #[test]
+        fn warn() {
+            let mut config = Map::new();
+            config.insert("warn_singletons".into(), Value::Boolean(true));
+
+            let tagger = Tagger::new(Some(&config)).unwrap();
+
+            assert!(tagger.check_singletons(&tags()).is_ok());
+            assert_eq!(vec!["\"once\" in ./a.md"], singletons(&tags()));
+
+            // warning doesn't drop the singletons from our tags page
+            let mut book = Book::new();
+            book.push_item(Chapter::new(
+                "a",
+                "`tag:once` `tag:twice`".into(),
+                PathBuf::from("./a.md"),
+                vec![],
+            ));
+            book.push_item(Chapter::new(
+                "b",
+                "`tag:twice`".into(),
+                PathBuf::from("./b.md"),
+                vec![],
+            ));
+            let book = tagger.tag_book(Path::new("."), book).unwrap();
+            let headers = match book.sections.last() {
+                Some(BookItem::Chapter(chapter)) => chapter
+                    .content
+                    .lines()
+                    .filter(|line| line.starts_with("## "))
+                    .map(String::from)
+                    .collect::<Vec<_>>(),
+                other => panic!("Expected our tags page, found {:?}", other),
+            };
+            assert_eq!(vec!["## `once`", "## `twice`"], headers);
+        }
+
+        #[test]
+        fn strict() {
+            let mut config = Map::new();
+            config.insert("strict_singletons".into(), Value::Boolean(true));
+
+            let tagger = Tagger::new(Some(&config)).unwrap();
+
+            let err = tagger.check_singletons(&tags()).unwrap_err();
+            assert_eq!(
+                "Found tags that are only used once: \"once\" in ./a.md",
+                err.to_string()
+            );
+        }
+    }
+
+    mod namespaces {
+        use super::*;
+        use toml::map::Map;
+
+        fn namespace(prefix: &str, filename: &str, title: &str) -> Value {
+            let mut namespace = Map::new();
+            namespace.insert("prefix".into(), Value::String(prefix.into()));
+            namespace.insert("filename".into(), Value::String(filename.into()));
+            namespace.insert("title".into(), Value::String(title.into()));
+
+            Value::Table(namespace)
+        }
+
+        #[test]
+        fn shorthand() {
+            let mut config = Map::new();
+            config.insert("prefix".into(), Value::String("topic:".into()));
+
+            let taggers = Tagger::namespaces(Some(&config)).unwrap();
+
+            assert_eq!(1, taggers.len());
+            assert_eq!("topic:", taggers[0].prefix);
+        }
+
+        #[test]
+        fn tag_book() {
+            let mut config = Map::new();
+            config.insert("show_counts".into(), Value::Boolean(true));
+            config.insert(
+                "namespaces".into(),
+                Value::Array(vec![
+                    namespace("topic:", "topics.md", "Topics"),
+                    namespace("audience:", "audiences.md", "Audiences"),
+                ]),
+            );
+
+            let mut book = Book::new();
+            book.push_item(Chapter::new(
+                CHAPTER_NAME,
+                "`topic:rust` `audience:beginner`".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            ));
+
+            for tagger in Tagger::namespaces(Some(&config)).unwrap() {
+                assert!(tagger.show_counts);
+                book = tagger.tag_book(Path::new("."), book).unwrap();
+            }
+
+            let chapters = book
+                .iter()
+                .filter_map(|item| match item {
+                    BookItem::Chapter(chapter) => Some(chapter),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+
+            assert_eq!(
+                vec![CHAPTER_NAME, "Topics", "Audiences"],
+                chapters.iter().map(|c| c.name.as_str()).collect::<Vec<_>>()
+            );
+            assert_eq!(
+                "<a id=\"tag-rust-1\"></a>[`#rust`](topics.md#rust \"Tag: rust\") \
+                 <a id=\"tag-beginner-2\"></a>[`#beginner`](audiences.md#beginner \"Tag: beginner\")",
+                chapters[0].content
+            );
+            assert!(chapters[1].content.contains("## `rust` (1)"));
+            assert!(chapters[2].content.contains("## `beginner` (1)"));
+        }
+
+        #[test]
+        fn shared_alias() {
+            let mut config = Map::new();
+            config.insert(
+                "namespaces".into(),
+                Value::Array(vec![
+                    namespace("topic:", "topics.md", "Topics"),
+                    namespace("audience:", "audiences.md", "Audiences"),
+                ]),
+            );
+
+            let mut book = Book::new();
+            book.push_item(Chapter::new(
+                CHAPTER_NAME,
+                "`topic:rust` `audience:rust`".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            ));
+
+            for tagger in Tagger::namespaces(Some(&config)).unwrap() {
+                book = tagger.tag_book(Path::new("."), book).unwrap();
+            }
+
+            let chapters = book
+                .iter()
+                .filter_map(|item| match item {
+                    BookItem::Chapter(chapter) => Some(chapter),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+
+            // each namespace links back to its own occurrence of `rust`
+            assert_eq!(
+                "<a id=\"tag-rust-1\"></a>[`#rust`](topics.md#rust \"Tag: rust\") \
+                 <a id=\"tag-rust-2\"></a>[`#rust`](audiences.md#rust \"Tag: rust\")",
+                chapters[0].content
+            );
+            assert!(chapters[1]
+                .content
+                .contains(&format!("(./{}#tag-rust-1 ", CHAPTER_FILE)));
+            assert!(chapters[2]
+                .content
+                .contains(&format!("(./{}#tag-rust-2 ", CHAPTER_FILE)));
+        }
+
+        #[test]
+        fn skip_other_pages() {
+            let mut topics = namespace("topic:", "topics.md", "Topics");
+            if let Value::Table(topics) = &mut topics {
+                topics.insert(
+                    "preamble".into(),
+                    Value::String("Try `audience:beginner` too".into()),
+                );
+            }
+            let mut config = Map::new();
+            config.insert(
+                "namespaces".into(),
+                Value::Array(vec![
+                    topics,
+                    namespace("audience:", "audiences.md", "Audiences"),
+                ]),
+            );
+
+            let mut book = Book::new();
+            book.push_item(Chapter::new(
+                CHAPTER_NAME,
+                "`topic:rust`".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            ));
+
+            for tagger in Tagger::namespaces(Some(&config)).unwrap() {
+                book = tagger.tag_book(Path::new("."), book).unwrap();
+            }
+
+            let chapters = book
+                .iter()
+                .filter_map(|item| match item {
+                    BookItem::Chapter(chapter) => Some(chapter),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+
+            // the audiences namespace never scanned our topics page
+            assert_eq!(
+                vec![CHAPTER_NAME, "Topics"],
+                chapters.iter().map(|c| c.name.as_str()).collect::<Vec<_>>()
+            );
+            assert!(chapters[1].content.contains("Try `audience:beginner` too"));
+        }
+
+        #[test]
+        fn duplicate_prefix() {
+            let mut config = Map::new();
+            config.insert(
+                "namespaces".into(),
+                Value::Array(vec![
+                    namespace("topic:", "topics.md", "Topics"),
+                    namespace("topic:", "audiences.md", "Audiences"),
+                ]),
+            );
+
+            let err = Tagger::namespaces(Some(&config)).err().unwrap();
+            assert_eq!(
+                "Multiple tag namespaces use the prefix \"topic:\"",
+                err.to_string()
+            );
+        }
+
+        #[test]
+        fn unique_keys_not_inherited() {
+            let mut config = Map::new();
+            config.insert("json_output".into(), Value::String("tags.json".into()));
+            config.insert("silent_prefix".into(), Value::String("quiet:".into()));
+            config.insert(
+                "namespaces".into(),
+                Value::Array(vec![
+                    namespace("topic:", "topics.md", "Topics"),
+                    namespace("audience:", "audiences.md", "Audiences"),
+                ]),
+            );
+
+            let taggers = Tagger::namespaces(Some(&config)).unwrap();
+
+            assert_eq!(
+                vec!["topic!:", "audience!:"],
+                taggers
+                    .iter()
+                    .map(|t| t.silent_prefix.as_str())
+                    .collect::<Vec<_>>()
+            );
+            assert!(taggers.iter().all(|t| t.json_output.is_none()));
+        }
+
+        #[test]
+        fn duplicate_output() {
+            let mut topics = namespace("topic:", "topics.md", "Topics");
+            let mut audiences = namespace("audience:", "audiences.md", "Audiences");
+            for namespace in [&mut topics, &mut audiences] {
+                if let Value::Table(namespace) = namespace {
+                    namespace.insert("atom_output".into(), Value::String("tags.xml".into()));
+                }
+            }
+            let mut config = Map::new();
+            config.insert("namespaces".into(), Value::Array(vec![topics, audiences]));
+
+            let err = Tagger::namespaces(Some(&config)).err().unwrap();
+            assert_eq!(
+                "Multiple tag namespaces write to \"tags.xml\"",
+                err.to_string()
+            );
+        }
+
+        #[test]
+        fn duplicate_silent_prefix() {
+            let mut topics = namespace("topic:", "topics.md", "Topics");
+            let mut audiences = namespace("audience:", "audiences.md", "Audiences");
+            for namespace in [&mut topics, &mut audiences] {
+                if let Value::Table(namespace) = namespace {
+                    namespace.insert("silent_prefix".into(), Value::String("quiet:".into()));
+                }
+            }
+            let mut config = Map::new();
+            config.insert("namespaces".into(), Value::Array(vec![topics, audiences]));
+
+            let err = Tagger::namespaces(Some(&config)).err().unwrap();
+            assert_eq!(
+                "Multiple tag namespaces use the silent prefix \"quiet:\"",
+                err.to_string()
+            );
+        }
+    }
+
+    mod directory {
+        use super::*;
+        use toml::map::Map;
+
+        #[test]
+        fn nested_tags_page() {
+            let mut config = Map::new();
+            config.insert("directory".into(), Value::String("meta/".into()));
+            config.insert("verify_links".into(), Value::Boolean(true));
+
+            let mut book = Book::new();
+            for path in ["./chapter.md", "./section/chapter.md"] {
+                book.push_item(Chapter::new(
+                    CHAPTER_NAME,
+                    "`tag:hello`".into(),
+                    PathBuf::from(path),
+                    vec![],
+                ));
+            }
+
+            let book = Tagger::new(Some(&config))
+                .unwrap()
+                .tag_book(Path::new("."), book)
+                .unwrap();
+
+            let contents = book
+                .iter()
+                .filter_map(|item| match item {
+                    BookItem::Chapter(chapter) => Some(chapter.content.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+
+            assert_eq!(
+                r#"<a id="tag-hello-1"></a>[`#hello`](meta/tags.md#hello "Tag: hello")"#,
+                contents[0]
+            );
+            assert_eq!(
+                r#"<a id="tag-hello-1"></a>[`#hello`](../meta/tags.md#hello "Tag: hello")"#,
+                contents[1]
+            );
+            assert!(contents[2].contains(&format!(
+                "* /[{0}](.././chapter.md#tag-hello-1 \"{0}\")\n\
+                 * /[{0}](.././section/chapter.md#tag-hello-1 \"{0}\")",
+                CHAPTER_NAME
+            )));
+            match book.sections.last() {
+                Some(BookItem::Chapter(chapter)) => {
+                    assert_eq!(PathBuf::from("./meta/tags.md"), chapter.path)
+                }
+                item => panic!("Expected a chapter, found {:?}", item),
+            }
+        }
+    }
+
+    mod min_count {
+        use super::*;
+        use toml::map::Map;
+
+        fn tagged_book(options: &[(&str, Value)]) -> Book {
+            let mut config = Map::new();
+            config.insert("min_count".into(), Value::Integer(2));
+            for (key, value) in options {
+                config.insert(key.to_string(), value.clone());
+            }
+
+            let mut book = Book::new();
+            for (name, content) in [("a", "`tag:once` `tag:hello`"), ("b", "`tag:hello`")] {
+                book.push_item(Chapter::new(
+                    name,
+                    content.into(),
+                    PathBuf::from(format!("./{}.md", name)),
+                    vec![],
+                ));
+            }
+
+            Tagger::new(Some(&config))
+                .unwrap()
+                .tag_book(Path::new("."), book)
+                .unwrap()
+        }
+
+        fn content(book: &Book, index: usize) -> &str {
+            match &book.sections[index] {
+                BookItem::Chapter(chapter) => &chapter.content,
+                item => panic!("Expected a chapter, found {:?}", item),
+            }
+        }
+
+        #[test]
+        fn rare_text() {
+            let book = tagged_book(&[]);
+
+            assert_eq!(
+                r#"`#once` <a id="tag-hello-2"></a>[`#hello`](tags.md#hello "Tag: hello")"#,
+                content(&book, 0)
+            );
+            assert!(content(&book, 3).contains("## `hello`"));
+            assert!(content(&book, 3).contains("(./a.md#tag-hello-2 "));
+            assert!(!content(&book, 3).contains("## `once`"));
+        }
+
+        #[test]
+        fn rare_link() {
+            let book = tagged_book(&[
+                ("rare_inline", Value::String("link".into())),
+                ("verify_links", Value::Boolean(true)),
+            ]);
+
+            assert_eq!(
+                r#"<a id="tag-once-1"></a>[`#once`](tags.md "Tag: once") <a id="tag-hello-2"></a>[`#hello`](tags.md#hello "Tag: hello")"#,
+                content(&book, 0)
+            );
+            assert!(!content(&book, 3).contains("## `once`"));
+        }
+
+        #[test]
+        fn rare_recorded() {
+            let book = tagged_book(&[
+                ("chapter_footer", Value::Boolean(true)),
+                ("embed_metadata", Value::Boolean(true)),
+            ]);
+
+            assert!(content(&book, 0).contains(
+                "Tagged: once, [hello](tags.md#hello \"Tag: hello\")\n\n<!-- tags: once, hello -->"
+            ));
+        }
+    }
+
+    mod idempotent {
+        use super::*;
+
+        #[test]
+        fn run_twice() {
+            let ctx: PreprocessorContext = serde_json::from_value(serde_json::json!({
+                "root": ".",
+                "config": mdbook::Config::default(),
+                "renderer": "html",
+                "mdbook_version": mdbook::MDBOOK_VERSION,
+            }))
+            .unwrap();
+
+            let mut book = Book::new();
+            book.push_item(Chapter::new(
+                CHAPTER_NAME,
+                "`tag:hello`".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            ));
+
+            let preprocessor = TagPreprocessor::new();
+            let once = preprocessor.run(&ctx, book.clone()).unwrap();
+
+            // a rebuild keeps our tags page around, but reloads the chapters we tagged
+            let mut rebuilt = once.clone();
+            rebuilt.sections[0] = book.sections[0].clone();
+            let twice = preprocessor.run(&ctx, rebuilt).unwrap();
+
+            let items = twice
+                .sections
+                .iter()
+                .map(|item| match item {
+                    BookItem::Chapter(chapter) => chapter.name.clone(),
+                    BookItem::Separator => "---".into(),
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(vec![CHAPTER_NAME, "---", "Tags"], items);
+            assert_eq!(once.sections, twice.sections);
+        }
+    }
+
+    mod dry_run {
+        use super::*;
+        use toml::map::Map;
+
+        #[test]
+        fn unmodified_book() {
+            let mut config = Map::new();
+            config.insert("dry_run".into(), Value::Boolean(true));
+
+            let mut book = Book::new();
+            book.push_item(Chapter::new(
+                CHAPTER_NAME,
+                "`tag:hello` `tag:world`".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            ));
+
+            let tagged = Tagger::new(Some(&config))
+                .unwrap()
+                .tag_book(Path::new("."), book.clone())
+                .unwrap();
+
+            assert_eq!(book.sections, tagged.sections);
+        }
+    }
+
+    mod always_generate {
+        use super::*;
+        use toml::map::Map;
+
+        fn tagged_book(always_generate: bool) -> Book {
+            let mut config = Map::new();
+            config.insert("always_generate".into(), Value::Boolean(always_generate));
+
+            let mut book = Book::new();
+            book.push_item(Chapter::new(
+                CHAPTER_NAME,
+                "no tags here".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            ));
+
+            Tagger::new(Some(&config))
+                .unwrap()
+                .tag_book(Path::new("."), book)
+                .unwrap()
+        }
+
+        #[test]
+        fn empty_book() {
+            assert_eq!(1, tagged_book(false).sections.len());
+        }
+
+        #[test]
+        fn empty_tags_page() {
+            let book = tagged_book(true);
+
+            match book.sections.last() {
+                Some(BookItem::Chapter(chapter)) => {
+                    assert_eq!("Tags", chapter.name);
+                    assert_eq!(PathBuf::from("./tags.md"), chapter.path);
+                    assert_eq!(
+                        "<!-- mdbook-tag -->\n\n# Tags\n\nNo tags yet",
+                        chapter.content
+                    );
+                }
+                item => panic!("Expected our tags page, found {:?}", item),
+            }
+        }
+    }
+
+    mod generated_pages {
+        use super::*;
+        use toml::map::Map;
+
+        #[test]
+        fn literal_preamble() {
+            let mut descriptions = Map::new();
+            descriptions.insert("hello".into(), Value::String("See `tag:world`".into()));
+            let mut config = Map::new();
+            config.insert("preamble".into(), Value::String("Try `tag:world`".into()));
+            config.insert("descriptions".into(), Value::Table(descriptions));
+
+            let mut book = Book::new();
+            book.push_item(Chapter::new(
+                CHAPTER_NAME,
+                "`tag:hello`".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            ));
+
+            let tagger = Tagger::new(Some(&config)).unwrap();
+            let once = tagger.tag_book(Path::new("."), book.clone()).unwrap();
+            // a rebuild keeps our tags page around but reloads our chapter, the page still isn't
+            // scanned
+            let mut rebuilt = once.clone();
+            rebuilt.sections[0] = book.sections[0].clone();
+            let twice = tagger.tag_book(Path::new("."), rebuilt).unwrap();
+
+            for book in &[once, twice] {
+                match &book.sections[..] {
+                    [_, BookItem::Separator, BookItem::Chapter(tags_page)] => {
+                        assert!(tags_page.content.contains("Try `tag:world`"));
+                        assert!(tags_page.content.contains("See `tag:world`"));
+                        assert!(!tags_page.content.contains("world\""));
+                    }
+                    other => panic!("Expected our chapter and tags page, found {:?}", other),
+                }
+            }
+        }
+    }
+
+    mod check_collisions {
+        use super::*;
+        use toml::map::Map;
+
+        #[test]
+        fn colliding_chapter() {
+            let mut config = Map::new();
+            config.insert("filename".into(), Value::String("intro.md".into()));
+
+            let mut book = Book::new();
+            book.push_item(Chapter::new(
+                "Intro",
+                "`tag:hello`".into(),
+                PathBuf::from("intro.md"),
+                vec![],
+            ));
+
+            let err = Tagger::new(Some(&config))
+                .unwrap()
+                .tag_book(Path::new("."), book)
+                .err()
+                .unwrap();
+            assert_eq!(
+                "The tags page intro.md collides with the chapter \"Intro\" at intro.md, configure \
+                 a different filename or directory",
+                err.to_string()
+            );
+        }
+
+        #[test]
+        fn chapter_named_tags() {
+            // a chapter of the book with our title and path isn't one of our old tags pages
+            let mut book = Book::new();
+            book.push_item(Chapter::new(
+                "Tags",
+                "`tag:hello`".into(),
+                PathBuf::from("tags.md"),
+                vec![],
+            ));
+
+            let err = Tagger::new(None)
+                .unwrap()
+                .tag_book(Path::new("."), book)
+                .err()
+                .unwrap();
+            assert_eq!(
+                "The tags page tags.md collides with the chapter \"Tags\" at tags.md, configure a \
+                 different filename or directory",
+                err.to_string()
+            );
+        }
+    }
+
+    mod check_links {
+        use super::*;
+        use toml::map::Map;
+
+        fn linked_book(tagger: &Tagger) -> (Book, HashMap<String, Vec<Tag>>, TagAnchors) {
+            let mut book = Book::new();
+            book.push_item(Chapter::new(
+                CHAPTER_NAME,
+                "# Chapter\n\n`tag:hello` `tag:lang/rust` `tag:Hello!`".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            ));
+
+            let tags = tagger.collect_tags(&book).unwrap();
+            let anchors = tagger.tag_anchors(&tags);
+            book.for_each_mut(|item| {
+                if let BookItem::Chapter(chapter) = item {
+                    tagger.link_chapter(chapter, &anchors).unwrap();
+                }
+            });
+
+            (book, tags, anchors)
+        }
+
+        #[test]
+        fn single() {
+            let tagger = Tagger::new(None).unwrap();
+            let (book, tags, anchors) = linked_book(&tagger);
+            let tags_page = tagger.build_tags_page(tags.clone()).unwrap();
+
+            assert!(tagger
+                .check_links(&book, &tags, &anchors, &tags_page)
+                .is_ok());
+        }
+
+        #[test]
+        fn explicit_anchors() {
+            let tagger = Tagger::new(None).unwrap();
+            let (book, tags, _) = linked_book(&tagger);
+            let tags_page = tagger.build_tags_page(tags).unwrap();
+            let chapter = match &book.sections[0] {
+                BookItem::Chapter(chapter) => chapter,
+                other => panic!("Expected our chapter, found {:?}", other),
+            };
+
+            // tools like mdbook-linkcheck only see our markdown, so every fragment we link to
+            // needs an `<a id>` of its own rather than relying on mdbook's header ids
+            let fragments = |content: &str, page: &str| {
+                regex::Regex::new(&format!(r#"\]\({}#([^ "]+)"#, regex::escape(page)))
+                    .unwrap()
+                    .captures_iter(content)
+                    .map(|caps| caps[1].to_string())
+                    .collect::<Vec<_>>()
+            };
+
+            let to_tags_page = fragments(&chapter.content, "tags.md");
+            assert_eq!(vec!["hello", "lang-rust", "hello-2"], to_tags_page);
+            for fragment in to_tags_page {
+                assert!(tags_page
+                    .content
+                    .contains(&format!("<a id=\"{}\"></a>", fragment)));
+            }
+
+            let to_chapter = fragments(&tags_page.content, &format!("./{}", CHAPTER_FILE));
+            assert_eq!(3, to_chapter.len());
+            for fragment in to_chapter {
+                assert!(chapter
+                    .content
+                    .contains(&format!("<a id=\"{}\"></a>", fragment)));
+            }
+        }
+
+        #[test]
+        fn per_tag() {
+            let mut config = Map::new();
+            config.insert("mode".into(), Value::String("per-tag".into()));
+
+            let tagger = Tagger::new(Some(&config)).unwrap();
+            let (book, tags, anchors) = linked_book(&tagger);
+            let tags_page = tagger.build_tags_page(tags.clone()).unwrap();
+
+            assert!(tagger
+                .check_links(&book, &tags, &anchors, &tags_page)
+                .is_ok());
+        }
+
+        #[test]
+        fn unresolved() {
+            let tagger = Tagger::new(None).unwrap();
+            let (mut book, tags, anchors) = linked_book(&tagger);
+            let mut tags_page = tagger.build_tags_page(tags.clone()).unwrap();
+
+            tags_page.content = "# Tags\n\n## `hello`\n".into();
+            if let BookItem::Chapter(chapter) = &mut book.sections[0] {
+                chapter.content = "# Chapter".into();
+            }
+
+            let err = tagger
+                .check_links(&book, &tags, &anchors, &tags_page)
+                .unwrap_err();
+            assert_eq!(
+                "Found tag links that won't resolve: \
+                 \"hello!\" -> ./chapter.md#tag-hello-3, \
+                 \"hello!\" -> tags.md#hello-2, \
+                 \"hello\" -> ./chapter.md#tag-hello-1, \
+                 \"lang/rust\" -> ./chapter.md#tag-lang-rust-2, \
+                 \"lang/rust\" -> tags.md#lang-rust",
+                err.to_string()
+            );
+        }
+    }
+
+    mod expand_tag_clouds {
+        use super::*;
+        use toml::map::Map;
+
+        #[test]
+        fn cloud() {
+            let tagger = Tagger::new(None).unwrap();
+
+            let chapter_tag = Tag {
+                chapter_name: "Chapter".into(),
+                path: PathBuf::from("./chapter.md"),
+                parent_names: vec![],
+                anchor: None,
+                snippet: None,
+                number: None,
+            };
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert("b".into(), vec![chapter_tag.clone()]);
+            tags.insert("a".into(), vec![chapter_tag.clone(), chapter_tag]);
+
+            let mut chapter = Chapter::new(
+                "Intro",
+                "# Intro\n\n`tags:cloud`".into(),
+                PathBuf::from("./sub/intro.md"),
+                vec![],
+            );
+            tagger.expand_tag_clouds(&mut chapter, &tags).unwrap();
+
+            assert_eq!(
+                r#"# Intro
+
+<span class="tag-cloud"><a class="tag-cloud-item" href="../tags.md#a" data-count="2">a</a> <a class="tag-cloud-item" href="../tags.md#b" data-count="1">b</a></span>"#,
+                chapter.content
+            );
+        }
+
+        #[test]
+        fn display_names() {
+            let mut display_names = Map::new();
+            display_names.insert("f".into(), Value::String("Eff".into()));
+            let mut config = Map::new();
+            config.insert("display_names".into(), Value::Table(display_names));
+            config.insert(
+                "strip_display_prefix".into(),
+                Value::Array(vec![Value::String("topic:".into())]),
+            );
+            let tagger = Tagger::new(Some(&config)).unwrap();
+
+            let chapter_tag = Tag {
+                chapter_name: "Chapter".into(),
+                path: PathBuf::from("./chapter.md"),
+                parent_names: vec![],
+                anchor: None,
+                snippet: None,
+                number: None,
+            };
+            let mut tags: HashMap<String, _> = HashMap::new();
+            for alias in &["f", "éclair", "topic:g"] {
+                tags.insert(alias.to_string(), vec![chapter_tag.clone()]);
+            }
+
+            let mut chapter = Chapter::new(
+                "Intro",
+                "`tags:cloud`".into(),
+                PathBuf::from("./intro.md"),
+                vec![],
+            );
+            tagger.expand_tag_clouds(&mut chapter, &tags).unwrap();
+
+            // sorted like our tags page, and named like it too
+            assert_eq!(
+                r#"<span class="tag-cloud"><a class="tag-cloud-item" href="tags.md#%C3%A9clair" data-count="1">éclair</a> <a class="tag-cloud-item" href="tags.md#f" data-count="1">Eff</a> <a class="tag-cloud-item" href="tags.md#topic-g" data-count="1">g</a></span>"#,
+                chapter.content
+            );
+        }
+
+        #[test]
+        fn no_cloud() {
+            let tagger = Tagger::new(None).unwrap();
+
+            let content = "# Intro\n\n`tag:a`";
+            let mut chapter =
+                Chapter::new("Intro", content.into(), PathBuf::from("./intro.md"), vec![]);
+            tagger
+                .expand_tag_clouds(&mut chapter, &HashMap::new())
+                .unwrap();
+
+            assert_eq!(content, chapter.content);
+        }
+    }
+
+    mod insert_tags_page {
+        use super::*;
+        use toml::map::Map;
+
+        fn book_items(config: Option<&Table>) -> Vec<String> {
+            let tagger = Tagger::new(config).unwrap();
+
+            let mut book = Book::new();
+            book.push_item(Chapter::new(
+                CHAPTER_NAME,
+                "".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            ));
+            tagger.insert_tags_page(&mut book, tagger.build_tags_page(HashMap::new()).unwrap());
+
+            book.sections
+                .iter()
+                .map(|item| match item {
+                    BookItem::Chapter(chapter) => chapter.name.clone(),
+                    BookItem::Separator => "---".into(),
+                })
+                .collect()
+        }
+
+        #[test]
+        fn end() {
+            assert_eq!(vec![CHAPTER_NAME, "---", "Tags"], book_items(None));
+        }
+
+        #[test]
+        fn start_without_separator() {
+            let mut config = Map::new();
+            config.insert("position".into(), Value::String("start".into()));
+            config.insert("separator".into(), Value::Boolean(false));
+
+            assert_eq!(vec!["Tags", CHAPTER_NAME], book_items(Some(&config)));
+        }
+
+        #[test]
+        fn start() {
+            let mut config = Map::new();
+            config.insert("position".into(), Value::String("start".into()));
+
+            assert_eq!(vec!["Tags", "---", CHAPTER_NAME], book_items(Some(&config)));
+        }
+
+        #[test]
+        fn parent() {
+            let mut config = Map::new();
+            config.insert("parent".into(), Value::String(CHAPTER_NAME.into()));
+
+            assert_eq!(vec![CHAPTER_NAME], book_items(Some(&config)));
+
+            let tagger = Tagger::new(Some(&config)).unwrap();
+            let mut book = Book::new();
+            book.push_item(Chapter::new(
+                CHAPTER_NAME,
+                "".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            ));
+            tagger.insert_tags_page(&mut book, tagger.build_tags_page(HashMap::new()).unwrap());
+
+            match &book.sections[0] {
+                BookItem::Chapter(chapter) => match &chapter.sub_items[..] {
+                    [BookItem::Chapter(tags_page)] => {
+                        assert_eq!("Tags", tags_page.name);
+                        assert_eq!(vec![CHAPTER_NAME], tags_page.parent_names);
+                    }
+                    items => panic!("Expected our tags page, found {:?}", items),
+                },
+                item => panic!("Expected a chapter, found {:?}", item),
+            }
+        }
+
+        #[test]
+        fn missing_parent() {
+            let mut config = Map::new();
+            config.insert("parent".into(), Value::String("Missing".into()));
+
+            assert_eq!(vec![CHAPTER_NAME, "---", "Tags"], book_items(Some(&config)));
+        }
+
+        #[test]
+        fn parent_keeps_separators() {
+            let mut config = Map::new();
+            config.insert("parent".into(), Value::String(CHAPTER_NAME.into()));
+            // our linked chapter has no tags left the second time around
+            config.insert("always_generate".into(), Value::Boolean(true));
+            let tagger = Tagger::new(Some(&config)).unwrap();
+
+            let child = Chapter::new(
+                "Child",
+                "`tag:hello`".into(),
+                PathBuf::from("./child.md"),
+                vec![CHAPTER_NAME.into()],
+            );
+            let mut chapter = Chapter::new(
+                CHAPTER_NAME,
+                "".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+            chapter.sub_items = vec![BookItem::Chapter(child), BookItem::Separator];
+            let mut book = Book::new();
+            book.push_item(chapter);
+
+            // a rebuild removes our old page from under the parent, but not the book's separator
+            let once = tagger.tag_book(Path::new("."), book).unwrap();
+            let twice = tagger.tag_book(Path::new("."), once).unwrap();
+
+            match &twice.sections[..] {
+                [BookItem::Chapter(chapter)] => match &chapter.sub_items[..] {
+                    [BookItem::Chapter(child), BookItem::Separator, BookItem::Chapter(tags_page)] =>
+                    {
+                        assert_eq!("Child", child.name);
+                        assert_eq!("Tags", tags_page.name);
+                    }
+                    items => panic!(
+                        "Expected our child, separator and tags page, found {:?}",
+                        items
+                    ),
+                },
+                items => panic!("Expected a chapter, found {:?}", items),
+            }
+        }
+    }
+
+    mod write_json {
+        use super::*;
+
+        #[test]
+        fn sorted() {
+            let tagger = Tagger::new(None).unwrap();
+
+            let tag = |chapter_name: &str, anchor: Option<&str>| Tag {
+                chapter_name: chapter_name.into(),
+                path: PathBuf::from(format!("./{}.md", chapter_name)),
+                parent_names: vec![],
+                anchor: anchor.map(String::from),
+                snippet: None,
+                number: None,
+            };
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert("b".into(), vec![tag("b", None)]);
+            tags.insert("a".into(), vec![tag("b", None), tag("a", Some("tag-a-1"))]);
+
+            let mut buf = vec![];
+            tagger.write_json(&tags, &mut buf).unwrap();
+
+            let expected = r#"{
+  "a": [
+    {
+      "chapter_name": "a",
+      "path": "./a.md",
+      "parent_names": [],
+      "anchor": "tag-a-1"
+    },
+    {
+      "chapter_name": "b",
+      "path": "./b.md",
+      "parent_names": []
+    }
+  ],
+  "b": [
+    {
+      "chapter_name": "b",
+      "path": "./b.md",
+      "parent_names": []
+    }
+  ]
+}"#;
+            assert_eq!(expected, String::from_utf8(buf).unwrap());
+        }
+    }
+
+    mod write_sitemap {
+        use super::*;
+
+        fn sitemap(mode: Mode) -> (usize, String) {
+            let tagger = TaggerBuilder::new()
+                .sitemap_output("tags-sitemap.xml")
+                .base_url("https://example.com/book/")
+                .mode(mode)
+                .build()
+                .unwrap();
+
+            let tag = |chapter_name: &str| Tag {
+                chapter_name: chapter_name.into(),
+                path: PathBuf::from(format!("./{}.md", chapter_name)),
+                parent_names: vec![],
+                anchor: None,
+                snippet: None,
+                number: None,
+            };
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert("rust".into(), vec![tag("a"), tag("b")]);
+            tags.insert("async".into(), vec![tag("a")]);
+            tags.insert("lang/c++".into(), vec![tag("b")]);
+
+            let mut buf = vec![];
+            tagger
+                .write_sitemap(&tags, &tagger.tag_anchors(&tags), &mut buf)
+                .unwrap();
+
+            (tags.len(), String::from_utf8(buf).unwrap())
+        }
+
+        #[test]
+        fn urls() {
+            let (count, sitemap) = sitemap(Mode::Single);
+
+            assert_eq!(count, sitemap.matches("<url>").count());
+            assert!(sitemap.contains("<loc>https://example.com/book/tags.html#async</loc>"));
+            assert!(sitemap.contains("<loc>https://example.com/book/tags.html#lang-c</loc>"));
+        }
+
+        #[test]
+        fn per_tag_urls() {
+            let (count, sitemap) = sitemap(Mode::PerTag);
+
+            assert_eq!(count, sitemap.matches("<url>").count());
+            assert!(sitemap.contains("<loc>https://example.com/book/tags/async.html</loc>"));
+            assert!(sitemap.contains("<loc>https://example.com/book/tags/lang-c.html</loc>"));
+            assert!(!sitemap.contains('#'));
+        }
+    }
+
+    mod write_atom {
+        use super::*;
+        use std::time::Duration;
+
+        #[test]
+        fn entries() {
+            let tagger = Tagger::new(None).unwrap();
+
+            let tag = |chapter_name: &str| Tag {
+                chapter_name: chapter_name.into(),
+                path: PathBuf::from(format!("./{}.md", chapter_name)),
+                parent_names: vec![],
+                anchor: None,
+                snippet: None,
+                number: None,
+            };
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert("rust".into(), vec![tag("a"), tag("b & c")]);
+            tags.insert("async".into(), vec![tag("a")]);
+            let mut draft = tag("draft");
+            draft.path = PathBuf::new();
+            tags.insert("draft".into(), vec![draft]);
+
+            let mut modified = HashMap::new();
+            modified.insert(PathBuf::from("a.md"), SystemTime::UNIX_EPOCH);
+            modified.insert(
+                PathBuf::from("b & c.md"),
+                SystemTime::UNIX_EPOCH + Duration::from_secs(951_782_400 + 3_723),
+            );
+
+            let mut buf = vec![];
+            tagger.write_atom(&tags, &modified, &mut buf).unwrap();
+
+            let expected = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>Tags</title>
+  <id>urn:mdbook-tag:tags.html</id>
+  <updated>2000-02-29T01:02:03Z</updated>
+  <author>
+    <name>Tags</name>
+  </author>
+  <link href="tags.html"/>
+  <entry>
+    <title>b &amp; c</title>
+    <id>urn:mdbook-tag:b%20&amp;%20c.html</id>
+    <link href="b%20&amp;%20c.html"/>
+    <updated>2000-02-29T01:02:03Z</updated>
+    <category term="rust"/>
+  </entry>
+  <entry>
+    <title>a</title>
+    <id>urn:mdbook-tag:a.html</id>
+    <link href="a.html"/>
+    <updated>1970-01-01T00:00:00Z</updated>
+    <category term="async"/>
+    <category term="rust"/>
+  </entry>
+</feed>
+"#;
+            assert_eq!(expected, String::from_utf8(buf).unwrap());
+        }
+
+        fn feed(tagger: &Tagger) -> String {
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert(
+                "rust".into(),
+                vec![Tag {
+                    chapter_name: "Chapter".into(),
+                    path: PathBuf::from("./chapter.md"),
+                    parent_names: vec![],
+                    anchor: None,
+                    snippet: None,
+                    number: None,
+                }],
+            );
+
+            let mut buf = vec![];
+            tagger.write_atom(&tags, &HashMap::new(), &mut buf).unwrap();
+
+            String::from_utf8(buf).unwrap()
+        }
+
+        #[test]
+        fn book_ids() {
+            let mut tagger = Tagger::new(None).unwrap();
+            tagger.book_title = Some("My Book".into());
+            tagger.authors = vec!["Ada".into(), "Grace".into()];
+
+            let atom = feed(&tagger);
+            assert!(atom.contains("  <id>urn:mdbook-tag:my-book:tags.html</id>\n"));
+            assert!(atom.contains("    <id>urn:mdbook-tag:my-book:chapter.html</id>\n"));
+            assert!(atom.contains("  <author>\n    <name>Ada</name>\n  </author>\n"));
+            assert!(atom.contains("  <author>\n    <name>Grace</name>\n  </author>\n"));
+
+            let tagger = Tagger {
+                base_url: Some("https://example.com/book/".into()),
+                ..tagger
+            };
+            let atom = feed(&tagger);
+            assert!(atom.contains("  <id>https://example.com/book/tags.html</id>\n"));
+            assert!(atom.contains("    <id>https://example.com/book/chapter.html</id>\n"));
+        }
+    }
+
+    mod build_tags_page {
+        use super::*;
+        use toml::map::Map;
+
+        #[test]
+        fn simple() {
+            let tagger = Tagger::new(None).unwrap();
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert(
+                "hello".into(),
+                vec![Tag {
+                    chapter_name: "Chapter".into(),
+                    path: PathBuf::from("./chapter.md"),
+                    parent_names: vec![],
+                    anchor: None,
+                    snippet: None,
+                    number: None,
+                }],
+            );
+            let expected = r#"# Tags
+
+<a id="hello"></a>
+
+## `hello`
+
+* /[Chapter](./chapter.md "Chapter")"#;
+
+            let chapter = tagger.build_tags_page(tags).unwrap();
+
+            assert_eq!("Tags", chapter.name);
+            assert_eq!(expected, chapter.content);
+        }
+
+        #[test]
+        fn unicode_aliases() {
+            let mut config = Map::new();
+            config.insert("group_by".into(), Value::String("letter".into()));
+
+            let tagger = Tagger::new(Some(&config)).unwrap();
+            let mut tags: HashMap<String, _> = HashMap::new();
+            for alias in &["日本語", "مرحبا", "egg", "Éclair", "eclair"] {
+                tags.insert(
+                    alias.to_string(),
+                    vec![Tag {
+                        chapter_name: "Chapter".into(),
+                        path: PathBuf::from("./chapter.md"),
+                        parent_names: vec![],
+                        anchor: Some(format!("tag-{}-1", slugify(alias))),
+                        snippet: None,
+                        number: None,
+                    }],
+                );
+            }
+
+            let chapter = tagger.build_tags_page(tags).unwrap();
+
+            let headers = chapter
+                .content
+                .lines()
+                .filter(|line| line.starts_with('#'))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                vec![
+                    "# Tags",
+                    "# E",
+                    "## `eclair`",
+                    "## `Éclair`",
+                    "## `egg`",
+                    "# م",
+                    "## `مرحبا`",
+                    "# 日",
+                    "## `日本語`",
+                ],
+                headers
+            );
+            assert!(chapter.content.contains(r#"<a id="日本語"></a>"#));
+            assert!(chapter.content.contains(
+                r#"/[Chapter](./chapter.md#tag-%E6%97%A5%E6%9C%AC%E8%AA%9E-1 "Chapter")"#
+            ));
+        }
+
+        #[test]
+        fn paths_with_spaces() {
+            let tagger = Tagger::new(None).unwrap();
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert(
+                "hello".into(),
+                vec![Tag {
+                    chapter_name: "My Chapter".into(),
+                    path: PathBuf::from("./my section/my chapter.md"),
+                    parent_names: vec![],
+                    anchor: Some("tag-hello-1".into()),
+                    snippet: None,
+                    number: None,
+                }],
+            );
+
+            let chapter = tagger.build_tags_page(tags).unwrap();
+
+            assert!(chapter.content.contains(
+                r#"/[My Chapter](./my%20section/my%20chapter.md#tag-hello-1 "My Chapter")"#
+            ));
+        }
+
+        #[test]
+        fn windows_paths() {
+            let tagger = Tagger::new(None).unwrap();
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert(
+                "hello".into(),
+                vec![Tag {
+                    chapter_name: "Chapter".into(),
+                    path: PathBuf::from(r".\section\chapter.md"),
+                    parent_names: vec![],
+                    anchor: None,
+                    snippet: None,
+                    number: None,
+                }],
+            );
+
+            let chapter = tagger.build_tags_page(tags).unwrap();
+
+            assert!(chapter
+                .content
+                .contains(r#"/[Chapter](./section/chapter.md "Chapter")"#));
+        }
+
+        #[test]
+        fn table_layout() {
+            let mut config = Map::new();
+            config.insert("layout".into(), Value::String("table".into()));
+            config.insert("show_counts".into(), Value::Boolean(true));
+
+            let tagger = Tagger::new(Some(&config)).unwrap();
+            let tag = |chapter_name: &str, anchor: &str| Tag {
+                chapter_name: chapter_name.into(),
+                path: PathBuf::from(format!("./{}.md", chapter_name.to_lowercase())),
+                parent_names: vec![],
+                anchor: Some(anchor.into()),
+                snippet: None,
+                number: None,
+            };
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert(
+                "hello".into(),
+                vec![tag("B", "tag-hello-1"), tag("A", "tag-hello-2")],
+            );
+            tags.insert("world".into(), vec![tag("A", "tag-world-1")]);
+            let expected = r#"# Tags
+
+|Tag|Chapters|
+|---|--------|
+|<a id="hello"></a>`hello` (2)|[A](./a.md#tag-hello-2 "A"), [B](./b.md#tag-hello-1 "B")|
+|<a id="world"></a>`world` (1)|[A](./a.md#tag-world-1 "A")|"#;
+
+            let chapter = tagger.build_tags_page(tags).unwrap();
+
+            assert_eq!(expected, chapter.content);
+        }
+
+        #[test]
+        fn table_pipes() {
+            let mut config = Map::new();
+            config.insert("layout".into(), Value::String("table".into()));
+            let tagger = Tagger::new(Some(&config)).unwrap();
+
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert(
+                "x|y".into(),
+                vec![Tag {
+                    chapter_name: "A|B".into(),
+                    path: PathBuf::from("./a|b.md"),
+                    parent_names: vec![],
+                    anchor: Some("tag-xy-1".into()),
+                    snippet: None,
+                    number: None,
+                }],
+            );
+
+            let content = tagger.build_tags_page(tags).unwrap().content;
+            assert!(
+                content.ends_with(
+                    r#"|<a id="xy"></a><code>x&#124;y</code>|[A\|B](./a\|b.md#tag-xy-1 "A\|B")|"#
+                ),
+                "{}",
+                content
+            );
+
+            // every row still has just our two cells
+            let mut options = md::Options::empty();
+            options.insert(md::Options::ENABLE_TABLES);
+            let cells = md::Parser::new_ext(&content, options)
+                .filter(|event| *event == md::Event::Start(md::Tag::TableCell))
+                .count();
+            assert_eq!(4, cells);
+        }
+
+        #[test]
+        fn anchor_links() {
+            let tagger = Tagger::new(None).unwrap();
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert(
+                "hello".into(),
+                vec![Tag {
+                    chapter_name: "Chapter".into(),
+                    path: PathBuf::from("./chapter.md"),
+                    parent_names: vec![],
+                    anchor: Some("tag-hello-3".into()),
+                    snippet: None,
+                    number: None,
+                }],
+            );
+            let expected = r#"# Tags
+
+<a id="hello"></a>
+
+## `hello`
+
+* /[Chapter](./chapter.md#tag-hello-3 "Chapter")"#;
+
+            let chapter = tagger.build_tags_page(tags).unwrap();
+
+            assert_eq!(expected, chapter.content);
+        }
+
+        #[test]
+        fn draft_tag() {
+            let tagger = Tagger::new(None).unwrap();
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert(
+                "hello".into(),
+                vec![Tag {
+                    chapter_name: "Draft".into(),
+                    path: PathBuf::new(),
+                    parent_names: vec![],
+                    anchor: None,
+                    snippet: None,
+                    number: None,
+                }],
+            );
+            let expected = r#"# Tags
+
+<a id="hello"></a>
+
+## `hello`
+
+* /Draft"#;
+
+            let chapter = tagger.build_tags_page(tags).unwrap();
+
+            assert_eq!(expected, chapter.content);
+        }
+
+        #[test]
+        fn alternative_file_name() {
+            let alternative_name = "my_tags.md";
+            let mut config = Map::new();
+            config.insert("filename".into(), Value::String(alternative_name.into()));
+
+            let tagger = Tagger::new(Some(&config)).unwrap();
+
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert(
+                "hello".into(),
+                vec![Tag {
+                    chapter_name: "Chapter".into(),
+                    path: PathBuf::from("./chapter.md"),
+                    parent_names: vec![],
+                    anchor: None,
+                    snippet: None,
+                    number: None,
+                }],
+            );
+
+            let chapter = tagger.build_tags_page(tags).unwrap();
+
+            assert_eq!(
+                PathBuf::from(format!("./{}", alternative_name)),
+                chapter.path
+            );
+        }
+
+        #[test]
+        fn alternative_title() {
+            let mut config = Map::new();
+            config.insert("title".into(), Value::String("Topics".into()));
+
+            let tagger = Tagger::new(Some(&config)).unwrap();
+
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert(
+                "hello".into(),
+                vec![Tag {
+                    chapter_name: "Chapter".into(),
+                    path: PathBuf::from("./chapter.md"),
+                    parent_names: vec![],
+                    anchor: None,
+                    snippet: None,
+                    number: None,
+                }],
+            );
+            let expected = r#"# Topics
+
+<a id="hello"></a>
+
+## `hello`
+
+* /[Chapter](./chapter.md "Chapter")"#;
+
+            let chapter = tagger.build_tags_page(tags).unwrap();
+
+            assert_eq!("Topics", chapter.name);
+            assert_eq!(expected, chapter.content);
+        }
+
+        #[test]
+        fn display_names() {
+            let mut display_names = Map::new();
+            display_names.insert("ci-cd".into(), Value::String("CI/CD".into()));
+            let mut config = Map::new();
+            config.insert("display_names".into(), Value::Table(display_names));
+
+            let tagger = Tagger::new(Some(&config)).unwrap();
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert(
+                "ci-cd".into(),
+                vec![Tag {
+                    chapter_name: "Chapter".into(),
+                    path: PathBuf::from("./chapter.md"),
+                    parent_names: vec![],
+                    anchor: None,
+                    snippet: None,
+                    number: None,
+                }],
+            );
+
+            let chapter = tagger.build_tags_page(tags).unwrap();
+
+            assert_eq!(
+                r#"# Tags
+
+<a id="ci-cd"></a>
+
+## `CI/CD`
+
+* /[Chapter](./chapter.md "Chapter")"#,
+                chapter.content
+            );
+
+            let mut chapter = Chapter::new(
+                CHAPTER_NAME,
+                "`tag:CI-CD`".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+            let tags = tagger.process_chapter(&mut chapter).unwrap();
+
+            assert_eq!("ci-cd", tags[0].alias);
+            assert_eq!(
+                r#"<a id="tag-ci-cd-1"></a>[`#CI/CD`](tags.md#ci-cd "Tag: CI-CD")"#,
+                chapter.content
+            );
+        }
+
+        #[test]
+        fn colors() {
+            let mut colors = Map::new();
+            colors.insert("lang".into(), Value::String("#dea584".into()));
+            let mut config = Map::new();
+            config.insert("colors".into(), Value::Table(colors));
+
+            let tagger = Tagger::new(Some(&config)).unwrap();
+            let mut chapter = Chapter::new(
+                CHAPTER_NAME,
+                "`tag:lang/rust` `tag:other`".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+            let tags = tagger.process_chapter(&mut chapter).unwrap();
+
+            // nested tags use the color of their parent
+            assert_eq!(
+                r##"<a id="tag-lang-rust-1"></a><span class="tag-badge" style="background: #dea584">[`#lang/rust`](tags.md#lang-rust "Tag: lang/rust")</span> <a id="tag-other-2"></a>[`#other`](tags.md#other "Tag: other")"##,
+                chapter.content
+            );
+
+            let page = tagger.build_tags_page(tagger.build_tags_map(tags)).unwrap();
+            assert!(page.content.contains(
+                r##"## <span class="tag-badge" style="background: #dea584">`lang`</span>"##
+            ));
+            assert!(page.content.contains(
+                r##"### <span class="tag-badge" style="background: #dea584">`rust`</span>"##
+            ));
+            assert!(page.content.contains("## `other`"));
+        }
+
+        #[test]
+        fn strip_display_prefix() {
+            let mut config = Map::new();
+            config.insert(
+                "strip_display_prefix".into(),
+                Value::Array(vec![Value::String("topic:".into())]),
+            );
+
+            let tagger = Tagger::new(Some(&config)).unwrap();
+            let mut chapter = Chapter::new(
+                CHAPTER_NAME,
+                "`tag:Topic:Rust`".into(),
+                PathBuf::from(format!("./{}", CHAPTER_FILE)),
+                vec![],
+            );
+            let tags = tagger.process_chapter(&mut chapter).unwrap();
+
+            // our alias keeps its prefix, only what we show is stripped
+            assert_eq!("topic:rust", tags[0].alias);
+            assert_eq!(
+                r#"<a id="tag-topic-rust-1"></a>[`#Rust`](tags.md#topic-rust "Tag: Topic:Rust")"#,
+                chapter.content
+            );
+
+            let chapter = tagger.build_tags_page(tagger.build_tags_map(tags)).unwrap();
+
+            assert_eq!(
+                r#"# Tags
+
+<a id="topic-rust"></a>
+
+## `rust`
+
+* /[Test Chapter](./chapter.md#tag-topic-rust-1 "Test Chapter")"#,
+                chapter.content
+            );
+        }
+
+        #[test]
+        fn external() {
+            let mut external = Map::new();
+            external.insert(
+                "tcp".into(),
+                Value::String("https://example.com/glossary#tcp".into()),
+            );
+            let mut config = Map::new();
+            config.insert("external".into(), Value::Table(external));
+            config.insert("verify_links".into(), Value::Boolean(true));
+
+            let mut book = Book::new();
+            book.push_item(Chapter::new(
+                "Chapter",
+                "`tag:tcp, hello`".into(),
+                PathBuf::from("./section/chapter.md"),
+                vec![],
+            ));
+
+            let book = Tagger::new(Some(&config))
+                .unwrap()
+                .tag_book(Path::new("."), book)
+                .unwrap();
+
+            match &book.sections[..] {
+                [BookItem::Chapter(chapter), BookItem::Separator, BookItem::Chapter(tags_page)] => {
+                    assert_eq!(
+                        r#"<a id="tag-tcp-1"></a>[`#tcp`](https://example.com/glossary#tcp "Tag: tcp") <a id="tag-hello-2"></a>[`#hello`](../tags.md#hello "Tag: hello")"#,
+                        chapter.content
+                    );
+                    assert_eq!(
+                        r#"<!-- mdbook-tag -->
+
+# Tags
+
+<a id="hello"></a>
+
+## `hello`
+
+* /[Chapter](./section/chapter.md#tag-hello-2 "Chapter")
+
+<a id="tcp"></a>
+
+## [`tcp`](https://example.com/glossary#tcp "tcp")
+
+* /[Chapter](./section/chapter.md#tag-tcp-1 "Chapter")"#,
+                        tags_page.content
+                    );
+                }
+                other => panic!("Expected our chapter and tags page, found {:?}", other),
+            }
+        }
+
+        #[test]
+        fn descriptions() {
+            let mut descriptions = Map::new();
+            descriptions.insert(
+                "Hello".into(),
+                Value::String("A *friendly* greeting".into()),
+            );
+            let mut config = Map::new();
+            config.insert("descriptions".into(), Value::Table(descriptions));
+
+            let tagger = Tagger::new(Some(&config)).unwrap();
+
+            let chapter_tag = Tag {
+                chapter_name: "Chapter".into(),
+                path: PathBuf::from("./chapter.md"),
+                parent_names: vec![],
+                anchor: None,
+                snippet: None,
+                number: None,
+            };
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert("hello".into(), vec![chapter_tag.clone()]);
+            tags.insert("world".into(), vec![chapter_tag]);
+
+            let expected = r#"# Tags
+
+<a id="hello"></a>
+
+## `hello`
+
+A *friendly* greeting
+
+* /[Chapter](./chapter.md "Chapter")
+
+<a id="world"></a>
+
+## `world`
+
+* /[Chapter](./chapter.md "Chapter")"#;
+
+            let chapter = tagger.build_tags_page(tags).unwrap();
+
+            assert_eq!(expected, chapter.content);
+        }
+
+        #[test]
+        fn collision_check() {
+            let chapter_tag = Tag {
+                chapter_name: "Chapter".into(),
+                path: PathBuf::from("./chapter.md"),
+                parent_names: vec![],
+                anchor: None,
+                snippet: None,
+                number: None,
+            };
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert("tags".into(), vec![chapter_tag.clone()]);
+            tags.insert("a".into(), vec![chapter_tag]);
+
+            let mut config = Map::new();
+            assert!(Tagger::new(Some(&config))
+                .unwrap()
+                .build_tags_page(tags.clone())
+                .is_ok());
+
+            config.insert("collision_check".into(), Value::Boolean(true));
+            let err = Tagger::new(Some(&config))
+                .unwrap()
+                .build_tags_page(tags.clone())
+                .unwrap_err();
+            assert_eq!(
+                "Found tag anchors that collide with the headers of the tags page tags.md: \
+                 \"tags\" with the title",
+                err.to_string()
+            );
+
+            config.insert("group_by".into(), Value::String("letter".into()));
+            let err = Tagger::new(Some(&config))
+                .unwrap()
+                .build_tags_page(tags)
+                .unwrap_err();
+            assert_eq!(
+                "Found tag anchors that collide with the headers of the tags page tags.md: \
+                 \"a\" with the \"A\" group header, \"tags\" with the title",
+                err.to_string()
+            );
+        }
+
+        #[test]
+        fn strip_numbering() {
+            let mut config = Map::new();
+            config.insert(
+                "chapter_name_transform".into(),
+                Value::String("strip-numbering".into()),
+            );
+            let tagger = Tagger::new(Some(&config)).unwrap();
+
+            let tag = |chapter_name: &str, path: &str| Tag {
+                chapter_name: chapter_name.into(),
+                path: PathBuf::from(path),
+                parent_names: vec![],
+                anchor: None,
+                snippet: None,
+                number: None,
+            };
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert(
+                "hello".into(),
+                vec![
+                    tag("1. Intro", "./intro.md"),
+                    tag("2.3. Setup", "./setup.md"),
+                    tag("1.5x Faster", "./faster.md"),
+                ],
+            );
+
+            let expected = r#"# Tags
+
+<a id="hello"></a>
+
+## `hello`
+
+* /[Intro](./intro.md "Intro")
+* /[1.5x Faster](./faster.md "1.5x Faster")
+* /[Setup](./setup.md "Setup")"#;
+
+            let chapter = tagger.build_tags_page(tags).unwrap();
+
+            assert_eq!(expected, chapter.content);
+        }
+
+        #[test]
+        fn deflist() {
+            let mut descriptions = Map::new();
+            descriptions.insert(
+                "Hello".into(),
+                Value::String("A *friendly*\ngreeting\n\nThat's only on the tag's page".into()),
+            );
+            let mut config = Map::new();
+            config.insert("layout".into(), Value::String("deflist".into()));
+            config.insert("descriptions".into(), Value::Table(descriptions));
+
+            let tagger = Tagger::new(Some(&config)).unwrap();
+
+            let tag = |chapter_name: &str| Tag {
+                chapter_name: chapter_name.into(),
+                path: PathBuf::from(format!("./{}.md", chapter_name)),
+                parent_names: vec![],
+                anchor: None,
+                snippet: None,
+                number: None,
+            };
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert("hello".into(), vec![tag("b"), tag("a")]);
+            tags.insert("world".into(), vec![tag("a")]);
+
+            let expected = r#"# Tags
+
+<a id="hello"></a>
+
+`hello`
+: A *friendly* greeting
+: [a](./a.md "a"), [b](./b.md "b")
+
+<a id="world"></a>
+
+`world`
+: [a](./a.md "a")
+"#;
+
+            let chapter = tagger.build_tags_page(tags).unwrap();
+
+            assert_eq!(expected, chapter.content);
+        }
+
+        #[test]
+        fn glossary() {
+            let mut descriptions = Map::new();
+            descriptions.insert(
+                "Hello".into(),
+                Value::String("A *friendly* greeting".into()),
+            );
+            let mut config = Map::new();
+            config.insert("layout".into(), Value::String("glossary".into()));
+            config.insert("descriptions".into(), Value::Table(descriptions));
+
+            let tagger = Tagger::new(Some(&config)).unwrap();
+
+            let chapter_tag = Tag {
+                chapter_name: "Chapter".into(),
+                path: PathBuf::from("./chapter.md"),
+                parent_names: vec![],
+                anchor: None,
+                snippet: None,
+                number: None,
+            };
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert("hello".into(), vec![chapter_tag.clone()]);
+            tags.insert("world".into(), vec![chapter_tag]);
+
+            let expected = r#"# Tags
+
+<a id="hello"></a>
+
+## `hello`
+
+A *friendly* greeting
+
+<a id="world"></a>
+
+## `world`"#;
+
+            let chapter = tagger.build_tags_page(tags).unwrap();
+
+            assert_eq!(expected, chapter.content);
+        }
+
+        #[test]
+        fn per_tag_mode() {
+            let mut config = Map::new();
+            config.insert("mode".into(), Value::String("per-tag".into()));
+
+            let tagger = Tagger::new(Some(&config)).unwrap();
+
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert(
+                "hello".into(),
+                vec![Tag {
+                    chapter_name: "Chapter".into(),
+                    path: PathBuf::from("./chapter.md"),
+                    parent_names: vec![],
+                    anchor: None,
+                    snippet: None,
+                    number: None,
+                }],
+            );
+
+            let chapter = tagger.build_tags_page(tags).unwrap();
+
+            assert_eq!(
+                r#"# Tags
+
+* [`hello`](tags/hello.md "hello")"#,
+                chapter.content
+            );
+            assert_eq!(1, chapter.sub_items.len());
+            match &chapter.sub_items[0] {
+                BookItem::Chapter(tag_chapter) => {
+                    assert_eq!("hello", tag_chapter.name);
+                    assert_eq!(PathBuf::from("./tags/hello.md"), tag_chapter.path);
+                    assert_eq!(vec!["Tags".to_string()], tag_chapter.parent_names);
+                    assert_eq!(
+                        r#"# `hello`
+
+* /[Chapter](.././chapter.md "Chapter")"#,
+                        tag_chapter.content
+                    );
+                }
+                item => panic!("Expected a tag chapter, found {:?}", item),
+            }
+        }
+
+        #[test]
+        fn per_tag_display_names() {
+            let mut display_names = Map::new();
+            display_names.insert("hello".into(), Value::String("Hello!".into()));
+            let mut config = Map::new();
+            config.insert("mode".into(), Value::String("per-tag".into()));
+            config.insert("display_names".into(), Value::Table(display_names));
+            config.insert(
+                "strip_display_prefix".into(),
+                Value::Array(vec![Value::String("topic:".into())]),
+            );
+            let tagger = Tagger::new(Some(&config)).unwrap();
+
+            let chapter_tag = Tag {
+                chapter_name: "Chapter".into(),
+                path: PathBuf::from("./chapter.md"),
+                parent_names: vec![],
+                anchor: None,
+                snippet: None,
+                number: None,
+            };
+            let mut tags: HashMap<String, _> = HashMap::new();
+            for alias in &["hello", "topic:rust"] {
+                tags.insert(alias.to_string(), vec![chapter_tag.clone()]);
+            }
+
+            let names = tagger
+                .build_tags_page(tags)
+                .unwrap()
+                .sub_items
+                .into_iter()
+                .map(|item| match item {
+                    BookItem::Chapter(chapter) => chapter.name,
+                    item => panic!("Expected a tag chapter, found {:?}", item),
+                })
+                .collect::<Vec<_>>();
+
+            assert_eq!(vec!["Hello!", "rust"], names);
+        }
+
+        #[test]
+        fn show_counts() {
+            let mut config = Map::new();
+            config.insert("show_counts".into(), Value::Boolean(true));
+
+            let tagger = Tagger::new(Some(&config)).unwrap();
+
+            let chapter_tag = Tag {
+                chapter_name: "Chapter".into(),
+                path: PathBuf::from("./chapter.md"),
+                parent_names: vec![],
+                anchor: None,
+                snippet: None,
+                number: None,
+            };
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert("hello".into(), vec![chapter_tag.clone(), chapter_tag]);
+
+            let expected = r#"# Tags
+
+<a id="hello"></a>
+
+## `hello` (2)
+
+* /[Chapter](./chapter.md "Chapter")
+* /[Chapter](./chapter.md "Chapter")"#;
+
+            let chapter = tagger.build_tags_page(tags).unwrap();
+
+            assert_eq!(expected, chapter.content);
+        }
+
+        #[test]
+        fn group_by_letter() {
+            let mut config = Map::new();
+            config.insert("group_by".into(), Value::String("letter".into()));
+
+            let tagger = Tagger::new(Some(&config)).unwrap();
+
+            let chapter_tag = Tag {
+                chapter_name: "Chapter".into(),
+                path: PathBuf::from("./chapter.md"),
+                parent_names: vec![],
+                anchor: None,
+                snippet: None,
+                number: None,
+            };
+            let mut tags: HashMap<String, _> = HashMap::new();
+            for alias in &["apple", "avocado", "banana", "2d", "~tilde"] {
+                tags.insert(alias.to_string(), vec![chapter_tag.clone()]);
+            }
+
+            let headers = tagger
+                .build_tags_page(tags)
+                .unwrap()
+                .content
+                .lines()
+                .filter(|line| line.starts_with('#'))
+                .map(String::from)
+                .collect::<Vec<_>>();
+
+            assert_eq!(
+                vec![
+                    "# Tags",
+                    "# #",
+                    "## `2d`",
+                    "## `~tilde`",
+                    "# A",
+                    "## `apple`",
+                    "## `avocado`",
+                    "# B",
+                    "## `banana`",
+                ],
+                headers
+            );
+        }
+
+        #[test]
+        fn nested_tags() {
+            let tagger = Tagger::new(None).unwrap();
+
+            let chapter_tag = Tag {
+                chapter_name: "Chapter".into(),
+                path: PathBuf::from("./chapter.md"),
+                parent_names: vec![],
+                anchor: None,
+                snippet: None,
+                number: None,
+            };
+            let mut tags: HashMap<String, _> = HashMap::new();
+            for alias in &["lang/rust", "lang/python", "lang-agnostic", "other"] {
+                tags.insert(alias.to_string(), vec![chapter_tag.clone()]);
+            }
+
+            let expected = r#"# Tags
+
+<a id="lang"></a>
+
+## `lang`
+
+<a id="lang-python"></a>
+
+### `python`
+
+* /[Chapter](./chapter.md "Chapter")
+
+<a id="lang-rust"></a>
+
+### `rust`
+
+* /[Chapter](./chapter.md "Chapter")
+
+<a id="lang-agnostic"></a>
+
+## `lang-agnostic`
+
+* /[Chapter](./chapter.md "Chapter")
+
+<a id="other"></a>
+
+## `other`
+
+* /[Chapter](./chapter.md "Chapter")"#;
+
+            let chapter = tagger.build_tags_page(tags).unwrap();
+
+            assert_eq!(expected, chapter.content);
+        }
+
+        #[test]
+        fn nested_tags_by_count() {
+            let mut config = Map::new();
+            config.insert("sort".into(), Value::String("count".into()));
+            let tagger = Tagger::new(Some(&config)).unwrap();
+
+            let chapter_tag = Tag {
+                chapter_name: "Chapter".into(),
+                path: PathBuf::from("./chapter.md"),
+                parent_names: vec![],
+                anchor: None,
+                snippet: None,
+                number: None,
+            };
+            let mut tags: HashMap<String, _> = HashMap::new();
+            for (alias, count) in &[("lang/rust", 3), ("go", 2), ("lang/c", 1)] {
+                tags.insert(alias.to_string(), vec![chapter_tag.clone(); *count]);
+            }
+
+            let headers = tagger
+                .build_tags_page(tags)
+                .unwrap()
+                .content
+                .lines()
+                .filter(|line| line.starts_with("##"))
+                .map(String::from)
+                .collect::<Vec<_>>();
+
+            assert_eq!(
+                vec!["## `lang`", "### `c`", "### `rust`", "## `go`"],
+                headers
+            );
+        }
+
+        #[test]
+        fn count_ties_collated() {
+            let mut config = Map::new();
+            config.insert("sort".into(), Value::String("count".into()));
+            let tagger = Tagger::new(Some(&config)).unwrap();
+
+            let chapter_tag = Tag {
+                chapter_name: "Chapter".into(),
+                path: PathBuf::from("./chapter.md"),
+                parent_names: vec![],
+                anchor: None,
+                snippet: None,
+                number: None,
+            };
+            let mut tags: HashMap<String, _> = HashMap::new();
+            for (alias, count) in &[("zebra", 1), ("éclair", 1), ("apple", 1), ("b", 2)] {
+                tags.insert(alias.to_string(), vec![chapter_tag.clone(); *count]);
+            }
+
+            let headers = tagger
+                .build_tags_page(tags)
+                .unwrap()
+                .content
+                .lines()
+                .filter(|line| line.starts_with("##"))
+                .map(String::from)
+                .collect::<Vec<_>>();
+
+            // equal counts are alphabetical like the rest of our page, `é` sorts with `e`
+            assert_eq!(
+                vec!["## `b`", "## `apple`", "## `éclair`", "## `zebra`"],
+                headers
+            );
+        }
+
+        #[test]
+        fn preamble() {
+            let mut config = Map::new();
+            config.insert(
+                "preamble".into(),
+                Value::String("Every [tag](intro.md) in the book".into()),
+            );
+
+            let tagger = Tagger::new(Some(&config)).unwrap();
+
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert(
+                "hello".into(),
+                vec![Tag {
+                    chapter_name: "Chapter".into(),
+                    path: PathBuf::from("./chapter.md"),
+                    parent_names: vec![],
+                    anchor: None,
+                    snippet: None,
+                    number: None,
+                }],
+            );
+            let expected = r#"# Tags
+
+Every [tag](intro.md) in the book
+
+<a id="hello"></a>
+
+## `hello`
+
+* /[Chapter](./chapter.md "Chapter")"#;
+
+            let chapter = tagger.build_tags_page(tags).unwrap();
+
+            assert_eq!(expected, chapter.content);
+        }
+
+        #[test]
+        fn tag_sorting() {
+            let tagger = Tagger::new(None).unwrap();
+
+            let chapter_tag = Tag {
+                chapter_name: "Chapter".into(),
+                path: PathBuf::from("./chapter.md"),
+                parent_names: vec![],
+                anchor: None,
+                snippet: None,
+                number: None,
+            };
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert("a".into(), vec![chapter_tag.clone()]);
+            tags.insert("b".into(), vec![chapter_tag]);
+
+            let expected = r#"# Tags
+
+<a id="a"></a>
+
+## `a`
+
+* /[Chapter](./chapter.md "Chapter")
+
+<a id="b"></a>
+
+## `b`
+
+* /[Chapter](./chapter.md "Chapter")"#;
+
+            let chapter = tagger.build_tags_page(tags).unwrap();
+
+            assert_eq!(expected, chapter.content);
+        }
+
+        #[test]
+        fn count_sorting() {
+            let mut config = Map::new();
+            config.insert("sort".into(), Value::String("count".into()));
+
+            let tagger = Tagger::new(Some(&config)).unwrap();
+
+            let chapter_tag = Tag {
+                chapter_name: "Chapter".into(),
+                path: PathBuf::from("./chapter.md"),
+                parent_names: vec![],
+                anchor: None,
+                snippet: None,
+                number: None,
+            };
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert("a".into(), vec![chapter_tag.clone()]);
+            tags.insert("c".into(), vec![chapter_tag.clone()]);
+            tags.insert("b".into(), vec![chapter_tag.clone(), chapter_tag]);
+
+            let headers = tagger
+                .build_tags_page(tags)
+                .unwrap()
+                .content
+                .lines()
+                .filter(|line| line.starts_with("## "))
+                .map(String::from)
+                .collect::<Vec<_>>();
+
+            assert_eq!(vec!["## `b`", "## `a`", "## `c`"], headers);
+        }
+
+        #[test]
+        fn weighted_count_sorting() {
+            let mut weights = Map::new();
+            weights.insert("C".into(), Value::Integer(3));
+            let mut config = Map::new();
+            config.insert("sort".into(), Value::String("count".into()));
+            config.insert("weights".into(), Value::Table(weights));
+
+            let tagger = Tagger::new(Some(&config)).unwrap();
+
+            let chapter_tag = Tag {
+                chapter_name: "Chapter".into(),
+                path: PathBuf::from("./chapter.md"),
+                parent_names: vec![],
+                anchor: None,
+                snippet: None,
+                number: None,
+            };
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert("a".into(), vec![chapter_tag.clone()]);
+            tags.insert("c".into(), vec![chapter_tag.clone()]);
+            tags.insert("b".into(), vec![chapter_tag.clone(), chapter_tag]);
+
+            let headers = tagger
+                .build_tags_page(tags)
+                .unwrap()
+                .content
+                .lines()
+                .filter(|line| line.starts_with("## "))
+                .map(String::from)
+                .collect::<Vec<_>>();
+
+            // `c` is only used once, but it counts for 3
+            assert_eq!(vec!["## `c`", "## `b`", "## `a`"], headers);
+        }
+
+        #[test]
+        fn sort_recent() {
+            let root =
+                std::env::temp_dir().join(format!("mdbook-tag-recent-{}", std::process::id()));
+            let src = root.join("src");
+            fs::create_dir_all(&src).unwrap();
+
+            let now = SystemTime::now();
+            let mut book = Book::new();
+            for (name, age) in [("old", Some(60)), ("new", Some(0)), ("missing", None)] {
+                let path = PathBuf::from(format!("{}.md", name));
+                if let Some(age) = age {
+                    let file = fs::File::create(src.join(&path)).unwrap();
+                    file.set_modified(now - std::time::Duration::from_secs(age))
+                        .unwrap();
+                }
+                book.push_item(Chapter::new(name, format!("`tag:{}`", name), path, vec![]));
+            }
+
+            let mut config = Map::new();
+            config.insert("sort".into(), Value::String("recent".into()));
+            let book = Tagger::new(Some(&config))
+                .unwrap()
+                .tag_book(&root, book)
+                .unwrap();
+            fs::remove_dir_all(&root).unwrap();
+
+            let headers = match book.sections.last() {
+                Some(BookItem::Chapter(chapter)) => chapter
+                    .content
+                    .lines()
+                    .filter(|line| line.starts_with("## "))
+                    .map(String::from)
+                    .collect::<Vec<_>>(),
+                item => panic!("Expected our tags page, found {:?}", item),
+            };
+
+            // we can't read when `missing` was modified, so it's listed last
+            assert_eq!(vec!["## `new`", "## `old`", "## `missing`"], headers);
+        }
+
+        #[test]
+        fn snippets() {
+            let tagger = Tagger::new(None).unwrap();
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert(
+                "hello".into(),
+                vec![Tag {
+                    chapter_name: "Chapter".into(),
+                    path: PathBuf::from("./chapter.md"),
+                    parent_names: vec![],
+                    anchor: None,
+                    snippet: Some("is a greeting".into()),
+                    number: None,
+                }],
+            );
+
+            let chapter = tagger.build_tags_page(tags).unwrap();
+
+            assert!(chapter
+                .content
+                .ends_with("* /[Chapter](./chapter.md \"Chapter\")  \n  *is a greeting*"));
+        }
+
+        #[test]
+        fn back_to_top() {
+            let mut config = Map::new();
+            config.insert("back_to_top".into(), Value::Boolean(true));
+
+            let tagger = Tagger::new(Some(&config)).unwrap();
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert(
+                "top".into(),
+                vec![Tag {
+                    chapter_name: "Chapter".into(),
+                    path: PathBuf::from("./chapter.md"),
+                    parent_names: vec![],
+                    anchor: None,
+                    snippet: None,
+                    number: None,
+                }],
+            );
+            let expected = r#"<a id="top"></a>
+
+# Tags
+
+<a id="top-2"></a>
+
+## `top`
+
+* /[Chapter](./chapter.md "Chapter")
+
+[↑ Top](#top "Back to top")"#;
+
+            let chapter = tagger.build_tags_page(tags).unwrap();
+
+            assert_eq!(expected, chapter.content);
+        }
+
+        #[test]
+        fn section_template() {
+            let mut config = Map::new();
+            config.insert(
+                "section_template".into(),
+                Value::String(
+                    "<div class=\"tag-section\">\n\n**{alias}** {count} {missing}\n\n{chapters}\n\n</div>"
+                        .into(),
+                ),
+            );
+
+            let tagger = Tagger::new(Some(&config)).unwrap();
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert(
+                "<{count}>".into(),
+                vec![Tag {
+                    chapter_name: "Chapter".into(),
+                    path: PathBuf::from("./chapter.md"),
+                    parent_names: vec![],
+                    anchor: None,
+                    snippet: None,
+                    number: None,
+                }],
+            );
+
+            let chapter = tagger.build_tags_page(tags).unwrap();
+
+            // our alias is escaped and never filled in itself
+            assert_eq!(
+                r#"# Tags
+
+<a id="count"></a>
+
+<div class="tag-section">
+
+**&lt;{count}&gt;** 1 {missing}
+
+* /[Chapter](./chapter.md "Chapter")
+
+</div>
+"#,
+                chapter.content
+            );
+            let html = mdbook::utils::render_markdown(&chapter.content, false);
+            assert!(html.contains("<div class=\"tag-section\">"));
+            assert!(
+                html.contains("<a href=\"./chapter.html\" title=\"Chapter\">Chapter</a>"),
+                "{}",
+                html
+            );
+        }
+
+        fn chapter_order(sort_chapters: &str) -> Vec<String> {
+            let mut config = Map::new();
+            config.insert("sort_chapters".into(), Value::String(sort_chapters.into()));
+            let tagger = Tagger::new(Some(&config)).unwrap();
+
+            let mut book = Book::new();
+            for (name, number) in [
+                ("a", Some(vec![2])),
+                ("b", Some(vec![1, 2])),
+                ("c", None),
+                ("z", Some(vec![1])),
+            ] {
+                let mut chapter = Chapter::new(
+                    name,
+                    "`tag!:hello`".into(),
+                    PathBuf::from(format!("./{}.md", name)),
+                    vec![],
+                );
+                chapter.number = number.map(SectionNumber);
+                book.push_item(chapter);
+            }
+
+            let tags = tagger.collect_tags(&book).unwrap();
+            tagger
+                .build_tags_page(tags)
+                .unwrap()
+                .content
+                .lines()
+                .filter(|line| line.starts_with("* "))
+                .map(String::from)
+                .collect()
+        }
+
+        #[test]
+        fn sort_chapters_by_name() {
+            assert_eq!(
+                vec![
+                    "* /[a](./a.md \"a\")",
+                    "* /[b](./b.md \"b\")",
+                    "* /[c](./c.md \"c\")",
+                    "* /[z](./z.md \"z\")"
+                ],
+                chapter_order("name")
+            );
+        }
+
+        #[test]
+        fn sort_chapters_by_book_order() {
+            assert_eq!(
+                vec![
+                    "* /[z](./z.md \"z\")",
+                    "* /[b](./b.md \"b\")",
+                    "* /[a](./a.md \"a\")",
+                    "* /[c](./c.md \"c\")"
+                ],
+                chapter_order("book-order")
+            );
+        }
+
+        #[test]
+        fn sort_chapters_by_title() {
+            let tag = |parent_names: &[&str], chapter_name: &str| Tag {
+                chapter_name: chapter_name.into(),
+                path: PathBuf::from(format!("./{}.md", chapter_name)),
+                parent_names: parent_names.iter().map(|p| p.to_string()).collect(),
+                anchor: None,
+                snippet: None,
+                number: None,
+            };
+            let names = |sort: ChapterSort| {
+                let mut tags = vec![
+                    tag(&["Zed"], "Alpha"),
+                    tag(&[], "Beta"),
+                    tag(&["Alpha"], "Gamma"),
+                ];
+                sort_chapters(&mut tags, sort);
+                tags.into_iter().map(|t| t.chapter_name).collect::<Vec<_>>()
+            };
+
+            assert_eq!(vec!["Gamma", "Beta", "Alpha"], names(ChapterSort::Name));
+            assert_eq!(vec!["Alpha", "Beta", "Gamma"], names(ChapterSort::Title));
+        }
+
+        fn truncated_page(layout: &str, chapter_layout: &str) -> String {
+            let mut config = Map::new();
+            config.insert("max_chapters_per_tag".into(), Value::Integer(2));
+            config.insert("layout".into(), Value::String(layout.into()));
+            config.insert(
+                "chapter_layout".into(),
+                Value::String(chapter_layout.into()),
+            );
+            let tagger = Tagger::new(Some(&config)).unwrap();
+
+            let tag = |chapter_name: &str| Tag {
+                chapter_name: chapter_name.into(),
+                path: PathBuf::from(format!("./{}.md", chapter_name)),
+                parent_names: vec![],
+                anchor: None,
+                snippet: None,
+                number: None,
+            };
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert(
+                "busy".into(),
+                vec![tag("e"), tag("d"), tag("c"), tag("b"), tag("a")],
+            );
+            tags.insert("quiet".into(), vec![tag("a"), tag("b")]);
+
+            tagger.build_tags_page(tags).unwrap().content
+        }
+
+        #[test]
+        fn max_chapters_per_tag() {
+            assert_eq!(
+                r#"# Tags
+
+<a id="busy"></a>
+
+## `busy`
+
+* /[a](./a.md "a")
+* /[b](./b.md "b")
+* +3 more
+
+<a id="quiet"></a>
+
+## `quiet`
+
+* /[a](./a.md "a")
+* /[b](./b.md "b")"#,
+                truncated_page("list", "list")
+            );
+            assert!(truncated_page("list", "inline")
+                .contains("/[a](./a.md \"a\"), /[b](./b.md \"b\"), +3 more\n"));
+            assert!(truncated_page("table", "list")
+                .contains("[a](./a.md \"a\"), [b](./b.md \"b\"), +3 more|"));
+        }
+
+        #[test]
+        fn write_tags_page() {
+            let tagger = Tagger::new(None).unwrap();
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert(
+                "hello".into(),
+                vec![Tag {
+                    chapter_name: "Chapter".into(),
+                    path: PathBuf::from("./chapter.md"),
+                    parent_names: vec![],
+                    anchor: None,
+                    snippet: None,
+                    number: None,
+                }],
+            );
+
+            let mut written = vec![];
+            tagger.write_tags_page(tags.clone(), &mut written).unwrap();
+
+            assert_eq!(
+                tagger.build_tags_page(tags).unwrap().content,
+                String::from_utf8(written).unwrap()
+            );
+        }
+
+        #[test]
+        fn heading_offset() {
+            let mut config = Map::new();
+            config.insert("heading_offset".into(), Value::Integer(1));
+
+            let tagger = Tagger::new(Some(&config)).unwrap();
+            let chapter_tag = Tag {
+                chapter_name: "Chapter".into(),
+                path: PathBuf::from("./chapter.md"),
+                parent_names: vec![],
+                anchor: None,
+                snippet: None,
+                number: None,
+            };
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert("rust".into(), vec![chapter_tag.clone()]);
+            tags.insert("a/b/c/d/e".into(), vec![chapter_tag]);
+
+            let headers = tagger
+                .build_tags_page(tags)
+                .unwrap()
+                .content
+                .lines()
+                .filter(|line| line.starts_with('#'))
+                .map(String::from)
+                .collect::<Vec<_>>();
+
+            assert_eq!(
+                vec![
+                    "## Tags",
+                    "### `a`",
+                    "#### `b`",
+                    "##### `c`",
+                    "###### `d`",
+                    "###### `e`",
+                    "### `rust`"
+                ],
+                headers
+            );
+        }
+
+        #[test]
+        fn jump_bar() {
+            let mut config = Map::new();
+            config.insert("jump_bar".into(), Value::Boolean(true));
+
+            let tagger = Tagger::new(Some(&config)).unwrap();
+            let chapter_tag = Tag {
+                chapter_name: "Chapter".into(),
+                path: PathBuf::from("./chapter.md"),
+                parent_names: vec![],
+                anchor: None,
+                snippet: None,
+                number: None,
+            };
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert("rust".into(), vec![chapter_tag.clone()]);
+            tags.insert("async".into(), vec![chapter_tag]);
+
+            let chapter = tagger.build_tags_page(tags).unwrap();
+            let jump_bar = "[async](#async \"async\") · [rust](#rust \"rust\")";
+
+            let jump_bar_start = chapter.content.find(jump_bar).unwrap();
+            let first_header = chapter.content.find("## ").unwrap();
+            assert!(jump_bar_start < first_header);
+        }
+
+        #[test]
+        fn related() {
+            let mut config = Map::new();
+            config.insert("related".into(), Value::Boolean(true));
+
+            let tagger = Tagger::new(Some(&config)).unwrap();
+            let tag = |chapter_name: &str| Tag {
+                chapter_name: chapter_name.into(),
+                path: PathBuf::from(format!("./{}.md", chapter_name)),
+                parent_names: vec![],
+                anchor: None,
+                snippet: None,
+                number: None,
+            };
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert("rust".into(), vec![tag("a"), tag("b")]);
+            tags.insert("async".into(), vec![tag("a"), tag("b")]);
+            tags.insert("wasm".into(), vec![tag("b")]);
+            tags.insert("lonely".into(), vec![tag("c")]);
+
+            let expected = r#"# Tags
 
-impl Tagger {
-    pub fn new(config: Option<&Table>) -> Tagger {
-        let output_filename: String = config
-            .and_then(|t| t.get("filename"))
-            .and_then(Value::as_str)
-            .unwrap_or("tags.md")
-            .into();
+<a id="async"></a>
 
-        Tagger { output_filename }
-    }
+## `async`
 
-    fn process_chapter(&self, chapter: &mut Chapter) -> Result<Vec<AliasedTag>> {
-        let mut buf = String::with_capacity(chapter.content.len());
-        let mut tags = vec![];
+**Related:** [rust](tags.md#rust "rust"), [wasm](tags.md#wasm "wasm")
 
-        let events = new_cmark_parser(&chapter.content).flat_map(|e| match e {
-            md::Event::Code(ref raw_code) => {
-                let code = raw_code.trim();
+* /[a](./a.md "a")
+* /[b](./b.md "b")
 
-                if code.find(TAG_STRING_PREFIX) == Some(0) && code.len() > TAG_STRING_PREFIX.len() {
-                    let alias = code[TAG_STRING_PREFIX.len()..].trim();
+<a id="lonely"></a>
 
-                    let tag = AliasedTag::new(
-                        alias,
-                        chapter.name.clone(),
-                        chapter.path.clone(),
-                        chapter.parent_names.clone(),
-                    );
+## `lonely`
 
-                    tags.push(tag);
-
-                    let hash = format!("#{}", alias);
-                    let link = md::Tag::Link(
-                        md::LinkType::Inline,
-                        format!(
-                            "{}{}{}",
-                            path_to_root(&chapter.path),
-                            self.output_filename,
-                            hash
-                        )
-                        .into(),
-                        format!("Tag: {}", alias).into(),
-                    );
+* /[c](./c.md "c")
 
-                    vec![
-                        md::Event::Start(link.clone()),
-                        md::Event::Code(hash.into()),
-                        md::Event::End(link),
-                    ]
-                } else {
-                    vec![e]
-                }
-            }
-            _ => vec![e],
-        });
+<a id="rust"></a>
 
-        cmark(events, &mut buf, None)
-            .map_err(|err| Error::from(format!("Markdown serialization failed: {}", err)))?;
+## `rust`
 
-        chapter.content = buf;
+**Related:** [async](tags.md#async "async"), [wasm](tags.md#wasm "wasm")
 
-        Ok(tags)
-    }
+* /[a](./a.md "a")
+* /[b](./b.md "b")
 
-    fn build_tags_page(&self, tags_map: HashMap<String, Vec<Tag>>) -> Result<Chapter> {
-        let mut buf = String::new();
+<a id="wasm"></a>
 
-        let mut contents = vec![
-            md::Event::Start(md::Tag::Header(1)),
-            md::Event::Text("Tags".into()),
-            md::Event::End(md::Tag::Header(1)),
-        ];
+## `wasm`
 
-        let mut sorted_tags = tags_map.into_iter().collect::<Vec<_>>();
-        sorted_tags.sort_by(|a, b| a.0.cmp(&b.0));
+**Related:** [async](tags.md#async "async"), [rust](tags.md#rust "rust")
 
-        for (alias, mut tags) in sorted_tags {
-            contents.push(md::Event::Start(md::Tag::Header(2)));
-            contents.push(md::Event::Code(alias.into()));
-            contents.push(md::Event::End(md::Tag::Header(2)));
+* /[b](./b.md "b")"#;
 
-            tags = {
-                // order our tags by their paths
-                let mut tags_sort_info = tags
-                    .into_iter()
-                    .map(|t| {
-                        let mut sort_names = t.parent_names.clone();
-                        sort_names.push(t.chapter_name.clone());
+            let chapter = tagger.build_tags_page(tags).unwrap();
 
-                        (t, sort_names)
-                    })
-                    .collect::<Vec<_>>();
-                tags_sort_info.sort_by(|a, b| a.1.cmp(&b.1));
+            assert_eq!(expected, chapter.content);
+        }
 
-                tags_sort_info.into_iter().map(|t| t.0).collect()
+        fn primary_tags() -> HashMap<String, Vec<Tag>> {
+            let tag = |chapter_name: &str| Tag {
+                chapter_name: chapter_name.into(),
+                path: PathBuf::from(format!("./{}.md", chapter_name)),
+                parent_names: vec![],
+                anchor: None,
+                snippet: None,
+                number: None,
             };
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert("hello".into(), vec![tag("b"), tag("a")]);
 
-            for Tag {
-                chapter_name,
-                path,
-                parent_names,
-            } in tags.into_iter()
-            {
-                let parent_path: String = if !parent_names.is_empty() {
-                    format!("/{}/", parent_names.join("/"))
-                } else {
-                    "/".into()
-                };
+            tags
+        }
 
-                contents.push(md::Event::Text(parent_path.into()));
+        #[test]
+        fn primary() {
+            let mut primary = Map::new();
+            primary.insert("Hello".into(), Value::String("b.md".into()));
+            let mut config = Map::new();
+            config.insert("primary".into(), Value::Table(primary));
 
-                let path_str: String = path
-                    .to_str()
-                    .ok_or_else(|| {
-                        ErrorKind::Io(io::Error::new(
-                            io::ErrorKind::NotFound,
-                            "Couldn't build output path",
-                        ))
-                    })?
-                    .into();
+            let tagger = Tagger::new(Some(&config)).unwrap();
+            let expected = r#"# Tags
 
-                let link = md::Tag::Link(
-                    md::LinkType::Inline,
-                    path_str.into(),
-                    chapter_name.clone().into(),
-                );
+<a id="hello"></a>
 
-                contents.push(md::Event::Start(link.clone()));
-                contents.push(md::Event::Text(chapter_name.into()));
-                contents.push(md::Event::End(link.clone()));
-                contents.push(md::Event::Text("\n\n".into()));
-            }
-        }
+## `hello`
 
-        cmark(contents.iter(), &mut buf, None)
-            .map_err(|err| Error::from(format!("Markdown serialization failed: {}", err)))?;
+**Defined in:** [b](./b.md "b")
 
-        Ok(Chapter {
-            name: "Tags".into(),
-            content: buf,
-            number: None,
-            sub_items: vec![],
-            path: format!("./{}", self.output_filename).into(),
-            parent_names: vec![],
-        })
-    }
-}
+* /[a](./a.md "a")
+* /[b](./b.md "b")"#;
 
-#[derive(Debug, PartialEq)]
-pub struct AliasedTag {
-    alias: String,
-    tag: Tag,
-}
+            let chapter = tagger.build_tags_page(primary_tags()).unwrap();
 
-impl AliasedTag {
-    fn new<S: Into<String>>(
-        alias: S,
-        chapter_name: String,
-        path: PathBuf,
-        parent_names: Vec<String>,
-    ) -> AliasedTag {
-        AliasedTag {
-            alias: alias.into().to_ascii_lowercase(),
-            tag: Tag {
-                chapter_name,
-                path,
-                parent_names,
-            },
+            assert_eq!(expected, chapter.content);
         }
-    }
-}
-
-#[derive(Debug, PartialEq, Clone)]
-pub struct Tag {
-    chapter_name: String,
-    path: PathBuf,
-    parent_names: Vec<String>,
-}
-
-#[cfg(test)]
-mod test {
-    use super::*;
 
-    static CHAPTER_NAME: &str = "Test Chapter";
-    static CHAPTER_FILE: &str = "chapter.md";
+        #[test]
+        fn primary_auto() {
+            let mut config = Map::new();
+            config.insert("primary_auto".into(), Value::Boolean(true));
 
-    mod process_chapter {
-        use super::*;
+            let tagger = Tagger::new(Some(&config)).unwrap();
 
-        static EXPECTED: &str = r#"# Chapter
+            let chapter = tagger.build_tags_page(primary_tags()).unwrap();
 
-[`#hello`](tags.md#hello "Tag: hello")"#;
+            assert!(chapter
+                .content
+                .contains(r#"**Defined in:** [a](./a.md "a")"#));
+            assert!(!Tagger::new(None)
+                .unwrap()
+                .build_tags_page(primary_tags())
+                .unwrap()
+                .content
+                .contains("Defined in"));
+        }
 
         #[test]
-        fn simple_chapter() {
-            let chapter = Chapter::new(
-                CHAPTER_NAME,
-                r#"# Chapter
+        fn inline_chapters() {
+            let mut config = Map::new();
+            config.insert("chapter_layout".into(), Value::String("inline".into()));
 
-`tag:hello`"#
-                    .into(),
-                PathBuf::from(format!("./{}", CHAPTER_FILE)),
-                vec![],
+            let tagger = Tagger::new(Some(&config)).unwrap();
+            let mut tags: HashMap<String, _> = HashMap::new();
+            tags.insert(
+                "hello".into(),
+                vec![
+                    Tag {
+                        chapter_name: "Chapter".into(),
+                        path: PathBuf::from("./chapter.md"),
+                        parent_names: vec![],
+                        anchor: None,
+                        snippet: None,
+                        number: None,
+                    },
+                    Tag {
+                        chapter_name: "Nested".into(),
+                        path: PathBuf::from("./section/nested.md"),
+                        parent_names: vec!["Section".into()],
+                        anchor: None,
+                        snippet: None,
+                        number: None,
+                    },
+                    Tag {
+                        chapter_name: "Draft".into(),
+                        path: PathBuf::new(),
+                        parent_names: vec![],
+                        anchor: None,
+                        snippet: None,
+                        number: None,
+                    },
+                ],
             );
 
-            verify_process_chapter(vec!["hello"], chapter, EXPECTED);
-        }
+            let chapter = tagger.build_tags_page(tags).unwrap();
 
-        #[test]
-        fn sub_dir_chapter() {
-            let chapter = Chapter::new(
-                CHAPTER_NAME,
-                r#"# Chapter
+            assert_eq!(
+                r#"# Tags
 
-`tag:hello`"#
-                    .into(),
-                PathBuf::from(format!("./subchapter/{}", CHAPTER_FILE)),
-                vec![],
-            );
+<a id="hello"></a>
 
-            verify_process_chapter(
-                vec!["hello"],
-                chapter,
-                r#"# Chapter
+## `hello`
 
-[`#hello`](../tags.md#hello "Tag: hello")"#,
+/[Chapter](./chapter.md "Chapter"), /Draft, /Section/[Nested](./section/nested.md "Nested")"#,
+                chapter.content
             );
         }
 
+        fn split_config() -> Map<String, Value> {
+            let mut config = Map::new();
+            config.insert("split".into(), Value::String("alpha-ranges".into()));
+            config.insert("split_size".into(), Value::Integer(2));
+
+            config
+        }
+
         #[test]
-        fn sub_chapter() {
-            let chapter = Chapter::new(
-                CHAPTER_NAME,
-                r#"# Chapter
+        fn alpha_ranges() {
+            let tagger = Tagger::new(Some(&split_config())).unwrap();
+            let mut tags: HashMap<String, _> = HashMap::new();
+            for alias in &["apple", "avocado", "banana", "cherry", "date", "~tilde"] {
+                tags.insert(
+                    alias.to_string(),
+                    vec![Tag {
+                        chapter_name: "Chapter".into(),
+                        path: PathBuf::from("./chapter.md"),
+                        parent_names: vec![],
+                        anchor: None,
+                        snippet: None,
+                        number: None,
+                    }],
+                );
+            }
 
-`tag:hello`"#
-                    .into(),
-                PathBuf::from(format!("./{}", CHAPTER_FILE)),
-                vec!["Parent One".into(), "Parent Two".into()],
-            );
+            let chapter = tagger.build_tags_page(tags).unwrap();
 
-            verify_process_chapter(vec!["hello"], chapter, EXPECTED);
-        }
+            assert_eq!(
+                r##"# Tags
 
-        fn verify_process_chapter(tag_aliases: Vec<&str>, mut chapter: Chapter, expected: &str) {
-            let tagger = Tagger::new(None);
-            let tags: Vec<_> = tag_aliases
-                .into_iter()
-                .map(|alias| {
-                    AliasedTag::new(
-                        alias,
-                        chapter.name.clone(),
-                        chapter.path.clone(),
-                        chapter.parent_names.clone(),
-                    )
+* [#](tags/symbols.md "#")
+* [A](tags/a.md "A")
+* [B–C](tags/b-c.md "B–C")
+* [D](tags/d.md "D")"##,
+                chapter.content
+            );
+            let pages = chapter
+                .sub_items
+                .iter()
+                .map(|item| match item {
+                    BookItem::Chapter(page) => (page.name.as_str(), page.path.clone()),
+                    BookItem::Separator => panic!("Expected only chapters"),
                 })
-                .collect();
+                .collect::<Vec<_>>();
+            assert_eq!(
+                vec![
+                    ("#", PathBuf::from("./tags/symbols.md")),
+                    ("A", PathBuf::from("./tags/a.md")),
+                    ("B–C", PathBuf::from("./tags/b-c.md")),
+                    ("D", PathBuf::from("./tags/d.md")),
+                ],
+                pages
+            );
+            match &chapter.sub_items[2] {
+                BookItem::Chapter(page) => assert_eq!(
+                    r#"# B–C
 
-            assert_eq!(tags, tagger.process_chapter(&mut chapter).unwrap());
+<a id="banana"></a>
 
-            assert_eq!(expected, chapter.content);
-        }
-    }
+## `banana`
 
-    mod build_tags_page {
-        use super::*;
-        use toml::map::Map;
+* /[Chapter](.././chapter.md "Chapter")
 
-        #[test]
-        fn simple() {
-            let tagger = Tagger::new(None);
-            let mut tags: HashMap<String, _> = HashMap::new();
-            tags.insert(
-                "hello".into(),
-                vec![Tag {
-                    chapter_name: "Chapter".into(),
-                    path: PathBuf::from("./chapter.md"),
-                    parent_names: vec![],
-                }],
-            );
-            let expected = r#"# Tags
+<a id="cherry"></a>
 
-## `hello`
+## `cherry`
 
-/[Chapter](./chapter.md "Chapter")
+* /[Chapter](.././chapter.md "Chapter")"#,
+                    page.content
+                ),
+                other => panic!("Expected a range page, found {:?}", other),
+            }
+        }
 
-"#;
+        #[test]
+        fn alpha_range_links() {
+            let mut config = split_config();
+            config.insert("verify_links".into(), Value::Boolean(true));
 
-            let chapter = tagger.build_tags_page(tags).unwrap();
+            let mut book = Book::new();
+            book.push_item(Chapter::new(
+                "Chapter",
+                "`tag:apple` `tag:banana` `tag:cherry`".into(),
+                PathBuf::from("./chapter.md"),
+                vec![],
+            ));
 
-            assert_eq!("Tags", chapter.name);
-            assert_eq!(expected, chapter.content);
+            let book = Tagger::new(Some(&config))
+                .unwrap()
+                .tag_book(Path::new("."), book)
+                .unwrap();
+
+            match &book.sections[0] {
+                BookItem::Chapter(chapter) => {
+                    assert!(chapter.content.contains("](tags/a-b.md#apple "));
+                    assert!(chapter.content.contains("](tags/c.md#cherry "));
+                }
+                other => panic!("Expected our chapter, found {:?}", other),
+            }
         }
 
         #[test]
-        fn alternative_file_name() {
-            let alternative_name = "my_tags.md";
+        fn featured() {
             let mut config = Map::new();
-            config.insert("filename".into(), Value::String(alternative_name.into()));
-
-            let tagger = Tagger::new(Some(&config));
+            config.insert(
+                "featured".into(),
+                Value::Array(vec![Value::String("World".into())]),
+            );
 
+            let tagger = Tagger::new(Some(&config)).unwrap();
             let mut tags: HashMap<String, _> = HashMap::new();
-            tags.insert(
-                "hello".into(),
-                vec![Tag {
-                    chapter_name: "Chapter".into(),
-                    path: PathBuf::from("./chapter.md"),
-                    parent_names: vec![],
-                }],
-            );
+            for alias in &["hello", "world"] {
+                tags.insert(
+                    alias.to_string(),
+                    vec![Tag {
+                        chapter_name: "Chapter".into(),
+                        path: PathBuf::from("./chapter.md"),
+                        parent_names: vec![],
+                        anchor: None,
+                        snippet: None,
+                        number: None,
+                    }],
+                );
+            }
 
             let chapter = tagger.build_tags_page(tags).unwrap();
 
             assert_eq!(
-                PathBuf::from(format!("./{}", alternative_name)),
-                chapter.path
+                r#"# Tags
+
+<a id="world"></a>
+
+## `world`
+
+* /[Chapter](./chapter.md "Chapter")
+
+[All Tags](tags/all.md "All Tags")"#,
+                chapter.content
             );
+            match &chapter.sub_items[..] {
+                [BookItem::Chapter(all_tags)] => {
+                    assert_eq!("All Tags", all_tags.name);
+                    assert_eq!(PathBuf::from("./tags/all.md"), all_tags.path);
+                    assert_eq!(vec!["Tags".to_string()], all_tags.parent_names);
+                    assert!(all_tags.content.contains("## `hello`"));
+                    assert!(all_tags.content.contains("## `world`"));
+                    assert!(all_tags
+                        .content
+                        .contains(r#"/[Chapter](.././chapter.md "Chapter")"#));
+                }
+                other => panic!("Expected a page of every tag, found {:?}", other),
+            }
         }
 
         #[test]
-        fn tag_sorting() {
-            let tagger = Tagger::new(None);
+        fn featured_links() {
+            let mut config = Map::new();
+            config.insert(
+                "featured".into(),
+                Value::Array(vec![Value::String("world".into())]),
+            );
+            config.insert("verify_links".into(), Value::Boolean(true));
+
+            let mut book = Book::new();
+            book.push_item(Chapter::new(
+                "Chapter",
+                "`tag:hello` `tag:world`".into(),
+                PathBuf::from("./chapter.md"),
+                vec![],
+            ));
+
+            let book = Tagger::new(Some(&config))
+                .unwrap()
+                .tag_book(Path::new("."), book)
+                .unwrap();
+
+            match &book.sections[0] {
+                BookItem::Chapter(chapter) => {
+                    assert!(chapter.content.contains("](tags/all.md#hello "));
+                    assert!(chapter.content.contains("](tags.md#world "));
+                }
+                other => panic!("Expected our chapter, found {:?}", other),
+            }
+        }
+
+        #[test]
+        fn pinned_order() {
+            let mut config = Map::new();
+            config.insert(
+                "order".into(),
+                Value::Array(vec![
+                    Value::String("D".into()),
+                    Value::String("missing".into()),
+                    Value::String("b".into()),
+                ]),
+            );
+
+            let tagger = Tagger::new(Some(&config)).unwrap();
 
             let chapter_tag = Tag {
                 chapter_name: "Chapter".into(),
                 path: PathBuf::from("./chapter.md"),
                 parent_names: vec![],
+                anchor: None,
+                snippet: None,
+                number: None,
             };
             let mut tags: HashMap<String, _> = HashMap::new();
-            tags.insert("a".into(), vec![chapter_tag.clone()]);
-            tags.insert("b".into(), vec![chapter_tag]);
-
-            let expected = r#"# Tags
-
-## `a`
-
-/[Chapter](./chapter.md "Chapter")
-
-## `b`
-
-/[Chapter](./chapter.md "Chapter")
-
-"#;
+            for alias in ["a", "b", "c", "d"] {
+                tags.insert(alias.into(), vec![chapter_tag.clone()]);
+            }
 
-            let chapter = tagger.build_tags_page(tags).unwrap();
+            let headers = tagger
+                .build_tags_page(tags)
+                .unwrap()
+                .content
+                .lines()
+                .filter(|line| line.starts_with("## "))
+                .map(String::from)
+                .collect::<Vec<_>>();
 
-            assert_eq!(expected, chapter.content);
+            assert_eq!(vec!["## `d`", "## `b`", "## `a`", "## `c`"], headers);
         }
 
         #[test]
         fn path_sorting() {
-            let tagger = Tagger::new(None);
+            let tagger = Tagger::new(None).unwrap();
 
             let mut tags: HashMap<String, _> = HashMap::new();
             tags.insert(
@@ -445,31 +9131,38 @@ mod test {
                         chapter_name: "a".into(),
                         path: PathBuf::from("./chapter.md"),
                         parent_names: vec![],
+                        anchor: None,
+                        snippet: None,
+                        number: None,
                     },
                     Tag {
                         chapter_name: "a".into(),
                         path: PathBuf::from("./chapter.md"),
                         parent_names: vec!["a".into()],
+                        anchor: None,
+                        snippet: None,
+                        number: None,
                     },
                     Tag {
                         chapter_name: "b".into(),
                         path: PathBuf::from("./chapter.md"),
                         parent_names: vec!["b".into()],
+                        anchor: None,
+                        snippet: None,
+                        number: None,
                     },
                 ],
             );
 
             let expected = r#"# Tags
 
-## `a`
-
-/[a](./chapter.md "a")
-
-/a/[a](./chapter.md "a")
+<a id="a"></a>
 
-/b/[b](./chapter.md "b")
+## `a`
 
-"#;
+* /[a](./chapter.md "a")
+* /a/[a](./chapter.md "a")
+* /b/[b](./chapter.md "b")"#;
 
             let chapter = tagger.build_tags_page(tags).unwrap();
 
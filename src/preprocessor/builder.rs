@@ -0,0 +1,821 @@
+use super::{
+    AliasSpaces, ChapterLayout, ChapterNameTransform, ChapterSort, Display, GroupBy, IgnoreInline,
+    Inline, Layout, LinkStyle, Mode, Normalize, Position, RareInline, Sort, Split, Tagger,
+    SILENT_TAG_STRING_PREFIX, TAG_STRING_PREFIX, TOP_ANCHOR,
+};
+use glob::Pattern;
+use mdbook::errors::{Error, Result};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+/// Builds a `Tagger` one option at a time, anything that isn't set keeps its default
+#[derive(Clone)]
+pub struct TaggerBuilder {
+    tagger: Tagger,
+    // these are keyed by alias, so they're normalized once we know how to when building
+    exclude: Vec<String>,
+    descriptions: Vec<(String, String)>,
+    display_names: Vec<(String, String)>,
+    external: Vec<(String, String)>,
+    weights: Vec<(String, usize)>,
+    colors: Vec<(String, String)>,
+    anchors: Vec<(String, String)>,
+    primary: Vec<(String, PathBuf)>,
+    synonyms: Vec<(String, String)>,
+    order: Vec<String>,
+    featured: Vec<String>,
+    ignore: Vec<String>,
+}
+
+impl Default for TaggerBuilder {
+    fn default() -> Self {
+        TaggerBuilder::new()
+    }
+}
+
+impl TaggerBuilder {
+    pub fn new() -> TaggerBuilder {
+        TaggerBuilder {
+            tagger: Tagger {
+                output_filename: "tags.md".into(),
+                directory: None,
+                delimiter: ",".into(),
+                title: "Tags".into(),
+                preamble: None,
+                prefix: TAG_STRING_PREFIX.into(),
+                silent_prefix: SILENT_TAG_STRING_PREFIX.into(),
+                case_sensitive: false,
+                normalize: Normalize::Lower,
+                alias_spaces: AliasSpaces::Keep,
+                display: Display::Hash,
+                title_format: "Tag: {alias}".into(),
+                anchor_prefix: "".into(),
+                link_style: LinkStyle::Inline,
+                inline: Inline::Link,
+                mode: Mode::Single,
+                layout: Layout::List,
+                chapter_layout: ChapterLayout::List,
+                show_counts: false,
+                back_to_top: false,
+                jump_bar: false,
+                related: false,
+                heading_offset: 0,
+                section_template: None,
+                group_by: GroupBy::None,
+                sort: Sort::Alpha,
+                sort_chapters: ChapterSort::Name,
+                chapter_name_transform: ChapterNameTransform::None,
+                order: vec![],
+                featured: vec![],
+                max_chapters_per_tag: None,
+                split: Split::None,
+                split_size: 50,
+                position: Position::End,
+                separator: true,
+                parent: None,
+                always_generate: false,
+                dedupe: true,
+                min_count: 1,
+                rare_inline: RareInline::Text,
+                warn_singletons: false,
+                strict_singletons: false,
+                strict_syntax: false,
+                exclude: vec![],
+                draft_parent: false,
+                json_output: None,
+                atom_output: None,
+                sitemap_output: None,
+                base_url: None,
+                verify_links: false,
+                collision_check: false,
+                summary: false,
+                report_untagged: false,
+                verbose: false,
+                dry_run: false,
+                scan_alt: false,
+                chapter_footer: false,
+                embed_metadata: false,
+                snippets: false,
+                snippet_length: 80,
+                descriptions: HashMap::new(),
+                display_names: HashMap::new(),
+                strip_display_prefix: vec![],
+                external: HashMap::new(),
+                weights: HashMap::new(),
+                colors: HashMap::new(),
+                custom_anchors: HashMap::new(),
+                primary: HashMap::new(),
+                primary_auto: false,
+                synonyms: HashMap::new(),
+                ignore: HashSet::new(),
+                ignore_inline: IgnoreInline::Text,
+                namespace_pages: vec![],
+                src: "src".into(),
+                book_title: None,
+                authors: vec![],
+                modified: HashMap::new(),
+                letter_ranges: None,
+            },
+            exclude: vec![],
+            descriptions: vec![],
+            display_names: vec![],
+            external: vec![],
+            weights: vec![],
+            colors: vec![],
+            anchors: vec![],
+            primary: vec![],
+            synonyms: vec![],
+            order: vec![],
+            featured: vec![],
+            ignore: vec![],
+        }
+    }
+
+    /// The filename of our tags page
+    pub fn filename<S: Into<String>>(&mut self, filename: S) -> &mut Self {
+        self.tagger.output_filename = filename.into();
+        self
+    }
+
+    /// The directory, relative to the book source, our tags page lives in
+    pub fn directory<S: Into<String>>(&mut self, directory: S) -> &mut Self {
+        let directory = directory.into();
+        let directory = directory.trim_matches('/');
+        self.tagger.directory = if directory.is_empty() {
+            None
+        } else {
+            Some(directory.into())
+        };
+        self
+    }
+
+    /// The separator between multiple tags in one span
+    pub fn delimiter<S: Into<String>>(&mut self, delimiter: S) -> &mut Self {
+        self.tagger.delimiter = delimiter.into();
+        self
+    }
+
+    /// The title and sidebar label of our tags page
+    pub fn title<S: Into<String>>(&mut self, title: S) -> &mut Self {
+        self.tagger.title = title.into();
+        self
+    }
+
+    /// Markdown shown at the top of our tags page
+    pub fn preamble<S: Into<String>>(&mut self, preamble: S) -> &mut Self {
+        self.tagger.preamble = Some(preamble.into());
+        self
+    }
+
+    /// The prefix marking a code span as a tag, this can't be empty
+    pub fn prefix<S: Into<String>>(&mut self, prefix: S) -> &mut Self {
+        self.tagger.prefix = prefix.into();
+        self
+    }
+
+    /// The prefix marking a code span as a silent tag, this can't be empty
+    pub fn silent_prefix<S: Into<String>>(&mut self, silent_prefix: S) -> &mut Self {
+        self.tagger.silent_prefix = silent_prefix.into();
+        self
+    }
+
+    pub fn case_sensitive(&mut self, case_sensitive: bool) -> &mut Self {
+        self.tagger.case_sensitive = case_sensitive;
+        self
+    }
+
+    pub fn normalize(&mut self, normalize: Normalize) -> &mut Self {
+        self.tagger.normalize = normalize;
+        self
+    }
+
+    pub fn alias_spaces(&mut self, alias_spaces: AliasSpaces) -> &mut Self {
+        self.tagger.alias_spaces = alias_spaces;
+        self
+    }
+
+    pub fn display(&mut self, display: Display) -> &mut Self {
+        self.tagger.display = display;
+        self
+    }
+
+    /// The title of each inline tag link, this must contain `{alias}`
+    pub fn title_format<S: Into<String>>(&mut self, title_format: S) -> &mut Self {
+        self.tagger.title_format = title_format.into();
+        self
+    }
+
+    /// Prepended to the id of each tag's anchor on our tags page, so they can't clash with headers
+    pub fn anchor_prefix<S: Into<String>>(&mut self, anchor_prefix: S) -> &mut Self {
+        self.tagger.anchor_prefix = anchor_prefix.into();
+        self
+    }
+
+    pub fn link_style(&mut self, link_style: LinkStyle) -> &mut Self {
+        self.tagger.link_style = link_style;
+        self
+    }
+
+    pub fn inline(&mut self, inline: Inline) -> &mut Self {
+        self.tagger.inline = inline;
+        self
+    }
+
+    pub fn mode(&mut self, mode: Mode) -> &mut Self {
+        self.tagger.mode = mode;
+        self
+    }
+
+    pub fn layout(&mut self, layout: Layout) -> &mut Self {
+        self.tagger.layout = layout;
+        self
+    }
+
+    pub fn chapter_layout(&mut self, chapter_layout: ChapterLayout) -> &mut Self {
+        self.tagger.chapter_layout = chapter_layout;
+        self
+    }
+
+    pub fn show_counts(&mut self, show_counts: bool) -> &mut Self {
+        self.tagger.show_counts = show_counts;
+        self
+    }
+
+    pub fn back_to_top(&mut self, back_to_top: bool) -> &mut Self {
+        self.tagger.back_to_top = back_to_top;
+        self
+    }
+
+    pub fn jump_bar(&mut self, jump_bar: bool) -> &mut Self {
+        self.tagger.jump_bar = jump_bar;
+        self
+    }
+
+    pub fn related(&mut self, related: bool) -> &mut Self {
+        self.tagger.related = related;
+        self
+    }
+
+    pub fn heading_offset(&mut self, heading_offset: usize) -> &mut Self {
+        self.tagger.heading_offset = heading_offset;
+        self
+    }
+
+    /// Markdown or html written in place of the header and chapter list of each tag on our
+    /// single tags page, with `{alias}`, `{count}` and `{chapters}` filled in
+    pub fn section_template<S: Into<String>>(&mut self, section_template: S) -> &mut Self {
+        self.tagger.section_template = Some(section_template.into());
+        self
+    }
+
+    pub fn group_by(&mut self, group_by: GroupBy) -> &mut Self {
+        self.tagger.group_by = group_by;
+        self
+    }
+
+    pub fn sort(&mut self, sort: Sort) -> &mut Self {
+        self.tagger.sort = sort;
+        self
+    }
+
+    pub fn sort_chapters(&mut self, sort_chapters: ChapterSort) -> &mut Self {
+        self.tagger.sort_chapters = sort_chapters;
+        self
+    }
+
+    pub fn chapter_name_transform(&mut self, transform: ChapterNameTransform) -> &mut Self {
+        self.tagger.chapter_name_transform = transform;
+        self
+    }
+
+    /// Tags pinned to the top of our tags page in this order
+    pub fn order<I, S>(&mut self, order: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.order = order.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Only these tags, in this order, are shown on our tags page. The rest are listed on a page of
+    /// every tag under our tags page.
+    pub fn featured<I, S>(&mut self, featured: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.featured = featured.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// The most chapters listed under each tag on a single tags page, the rest are counted
+    pub fn max_chapters_per_tag(&mut self, max_chapters_per_tag: usize) -> &mut Self {
+        self.tagger.max_chapters_per_tag = Some(max_chapters_per_tag);
+        self
+    }
+
+    pub fn split(&mut self, split: Split) -> &mut Self {
+        self.tagger.split = split;
+        self
+    }
+
+    /// The number of tags on each page when our tags page is split
+    pub fn split_size(&mut self, split_size: usize) -> &mut Self {
+        self.tagger.split_size = split_size.max(1);
+        self
+    }
+
+    pub fn position(&mut self, position: Position) -> &mut Self {
+        self.tagger.position = position;
+        self
+    }
+
+    pub fn separator(&mut self, separator: bool) -> &mut Self {
+        self.tagger.separator = separator;
+        self
+    }
+
+    /// The name of the chapter our tags page is nested under
+    pub fn parent<S: Into<String>>(&mut self, parent: S) -> &mut Self {
+        self.tagger.parent = Some(parent.into());
+        self
+    }
+
+    pub fn always_generate(&mut self, always_generate: bool) -> &mut Self {
+        self.tagger.always_generate = always_generate;
+        self
+    }
+
+    pub fn dedupe(&mut self, dedupe: bool) -> &mut Self {
+        self.tagger.dedupe = dedupe;
+        self
+    }
+
+    /// Tags used fewer times than this are left off of our tags page
+    pub fn min_count(&mut self, min_count: usize) -> &mut Self {
+        self.tagger.min_count = min_count.max(1);
+        self
+    }
+
+    pub fn rare_inline(&mut self, rare_inline: RareInline) -> &mut Self {
+        self.tagger.rare_inline = rare_inline;
+        self
+    }
+
+    pub fn warn_singletons(&mut self, warn_singletons: bool) -> &mut Self {
+        self.tagger.warn_singletons = warn_singletons;
+        self
+    }
+
+    pub fn strict_singletons(&mut self, strict_singletons: bool) -> &mut Self {
+        self.tagger.strict_singletons = strict_singletons;
+        self
+    }
+
+    /// Fail instead of warning about tag spans without any aliases, like `tag:`
+    pub fn strict_syntax(&mut self, strict_syntax: bool) -> &mut Self {
+        self.tagger.strict_syntax = strict_syntax;
+        self
+    }
+
+    /// Glob patterns for the chapters that shouldn't be scanned for tags
+    pub fn exclude<I, S>(&mut self, patterns: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.exclude = patterns.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn draft_parent(&mut self, draft_parent: bool) -> &mut Self {
+        self.tagger.draft_parent = draft_parent;
+        self
+    }
+
+    /// The directory our chapters live in relative to the root of our book, only used to read
+    /// when our chapters were modified for `sort = "recent"`
+    pub fn src<P: Into<PathBuf>>(&mut self, src: P) -> &mut Self {
+        self.tagger.src = src.into();
+        self
+    }
+
+    /// The path, relative to the book root, our tags are written to as JSON
+    pub fn json_output<P: Into<PathBuf>>(&mut self, json_output: P) -> &mut Self {
+        self.tagger.json_output = Some(json_output.into());
+        self
+    }
+
+    /// The path, relative to the book root, an Atom feed of our tagged chapters is written to
+    pub fn atom_output<P: Into<PathBuf>>(&mut self, atom_output: P) -> &mut Self {
+        self.tagger.atom_output = Some(atom_output.into());
+        self
+    }
+
+    /// The path, relative to the book root, a sitemap of the anchors of our tags is written to
+    pub fn sitemap_output<P: Into<PathBuf>>(&mut self, sitemap_output: P) -> &mut Self {
+        self.tagger.sitemap_output = Some(sitemap_output.into());
+        self
+    }
+
+    /// The url our book is served from, our sitemap needs absolute urls
+    pub fn base_url<S: Into<String>>(&mut self, base_url: S) -> &mut Self {
+        self.tagger.base_url = Some(base_url.into());
+        self
+    }
+
+    pub fn verify_links(&mut self, verify_links: bool) -> &mut Self {
+        self.tagger.verify_links = verify_links;
+        self
+    }
+
+    pub fn collision_check(&mut self, collision_check: bool) -> &mut Self {
+        self.tagger.collision_check = collision_check;
+        self
+    }
+
+    pub fn summary(&mut self, summary: bool) -> &mut Self {
+        self.tagger.summary = summary;
+        self
+    }
+
+    pub fn report_untagged(&mut self, report_untagged: bool) -> &mut Self {
+        self.tagger.report_untagged = report_untagged;
+        self
+    }
+
+    pub fn verbose(&mut self, verbose: bool) -> &mut Self {
+        self.tagger.verbose = verbose;
+        self
+    }
+
+    /// Log the tags page we would add instead of modifying our book
+    pub fn dry_run(&mut self, dry_run: bool) -> &mut Self {
+        self.tagger.dry_run = dry_run;
+        self
+    }
+
+    /// Collect the tags written in the alt text of our chapters' images
+    pub fn scan_alt(&mut self, scan_alt: bool) -> &mut Self {
+        self.tagger.scan_alt = scan_alt;
+        self
+    }
+
+    /// Add a `Tagged: rust, async` line linking to each chapter's tags to the end of it
+    pub fn chapter_footer(&mut self, chapter_footer: bool) -> &mut Self {
+        self.tagger.chapter_footer = chapter_footer;
+        self
+    }
+
+    /// Add a `<!-- tags: rust, async -->` comment listing each chapter's tags to the end of it
+    pub fn embed_metadata(&mut self, embed_metadata: bool) -> &mut Self {
+        self.tagger.embed_metadata = embed_metadata;
+        self
+    }
+
+    /// Show the text following each tag under its chapter on our tags page
+    pub fn snippets(&mut self, snippets: bool) -> &mut Self {
+        self.tagger.snippets = snippets;
+        self
+    }
+
+    /// The number of characters our snippets are truncated to
+    pub fn snippet_length(&mut self, snippet_length: usize) -> &mut Self {
+        self.tagger.snippet_length = snippet_length.max(1);
+        self
+    }
+
+    /// A markdown description shown under a tag on our tags page
+    pub fn description<A: Into<String>, D: Into<String>>(
+        &mut self,
+        alias: A,
+        description: D,
+    ) -> &mut Self {
+        self.descriptions.push((alias.into(), description.into()));
+        self
+    }
+
+    /// The name a tag is shown with on our tags page and in our chapters, it's still looked up and
+    /// linked to by its alias
+    pub fn display_name<A: Into<String>, N: Into<String>>(
+        &mut self,
+        alias: A,
+        name: N,
+    ) -> &mut Self {
+        self.display_names.push((alias.into(), name.into()));
+        self
+    }
+
+    /// Prefixes left off of the names of our tags wherever they're shown, our aliases keep them
+    pub fn strip_display_prefix<I, S>(&mut self, prefixes: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.tagger.strip_display_prefix = prefixes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// The url a tag links to from our chapters instead of our tags page, its chapters are still
+    /// listed on our tags page
+    pub fn external<A: Into<String>, U: Into<String>>(&mut self, alias: A, url: U) -> &mut Self {
+        self.external.push((alias.into(), url.into()));
+        self
+    }
+
+    /// Multiply how often a tag is used by `weight` when sorting by count and sizing tag clouds
+    pub fn weight<A: Into<String>>(&mut self, alias: A, weight: usize) -> &mut Self {
+        self.weights.push((alias.into(), weight));
+        self
+    }
+
+    /// The css color of the badge a tag and its nested tags are shown in, for the html renderer
+    pub fn color<A: Into<String>, C: Into<String>>(&mut self, alias: A, color: C) -> &mut Self {
+        self.colors.push((alias.into(), color.into()));
+        self
+    }
+
+    /// The id a tag's anchor on our tags page gets in place of its slug, for links from elsewhere
+    /// that need to stay put
+    pub fn anchor<A: Into<String>, I: Into<String>>(&mut self, alias: A, id: I) -> &mut Self {
+        self.anchors.push((alias.into(), id.into()));
+        self
+    }
+
+    /// The chapter, relative to the book source, a tag is defined in
+    pub fn primary<A: Into<String>, P: Into<PathBuf>>(&mut self, alias: A, path: P) -> &mut Self {
+        self.primary.push((alias.into(), path.into()));
+        self
+    }
+
+    pub fn primary_auto(&mut self, primary_auto: bool) -> &mut Self {
+        self.tagger.primary_auto = primary_auto;
+        self
+    }
+
+    /// List the occurrences of `synonym` under `canonical`
+    pub fn synonym<S: Into<String>, C: Into<String>>(
+        &mut self,
+        synonym: S,
+        canonical: C,
+    ) -> &mut Self {
+        self.synonyms.push((synonym.into(), canonical.into()));
+        self
+    }
+
+    /// Tags to leave off of our tags page
+    pub fn ignore<I, S>(&mut self, aliases: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.ignore = aliases.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn ignore_inline(&mut self, ignore_inline: IgnoreInline) -> &mut Self {
+        self.tagger.ignore_inline = ignore_inline;
+        self
+    }
+
+    pub fn build(&self) -> Result<Tagger> {
+        let mut tagger = self.tagger.clone();
+
+        // an empty prefix would turn every code span into a tag
+        if tagger.prefix.is_empty() || tagger.silent_prefix.is_empty() {
+            return Err(Error::from("Tag prefixes can't be empty"));
+        }
+        if !tagger.title_format.contains("{alias}") {
+            return Err(Error::from(format!(
+                "Invalid title_format \"{}\": it must contain {{alias}} to show the tag's name",
+                tagger.title_format
+            )));
+        }
+
+        if tagger.sitemap_output.is_some() && tagger.base_url.is_none() {
+            return Err(Error::from(
+                "sitemap_output needs a base_url to build the absolute urls of our sitemap",
+            ));
+        }
+        // both move our tags onto pages nested under our tags page, and only one can win
+        if !self.featured.is_empty() && tagger.split != Split::None {
+            return Err(Error::from(
+                "featured and split can't be used together, pick one way to break up the tags page",
+            ));
+        }
+
+        tagger.exclude = self
+            .exclude
+            .iter()
+            .map(|pattern| {
+                Pattern::new(pattern).map_err(|err| {
+                    Error::from(format!("Invalid exclude pattern \"{}\": {}", pattern, err))
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        // our configured aliases need to follow the same normalization as the ones in our chapters
+        tagger.synonyms = self
+            .synonyms
+            .iter()
+            .map(|(synonym, canonical)| {
+                (
+                    tagger.normalize_case(synonym),
+                    tagger.normalize_case(canonical),
+                )
+            })
+            .collect();
+        tagger.descriptions = self
+            .descriptions
+            .iter()
+            .map(|(alias, description)| (tagger.normalize_alias(alias), description.clone()))
+            .collect();
+        tagger.display_names = self
+            .display_names
+            .iter()
+            .map(|(alias, name)| (tagger.normalize_alias(alias), name.clone()))
+            .collect();
+        tagger.external = self
+            .external
+            .iter()
+            .map(|(alias, url)| (tagger.normalize_alias(alias), url.clone()))
+            .collect();
+        tagger.weights = self
+            .weights
+            .iter()
+            .map(|(alias, weight)| (tagger.normalize_alias(alias), *weight))
+            .collect();
+        tagger.colors = self
+            .colors
+            .iter()
+            .map(|(alias, color)| (tagger.normalize_alias(alias), color.clone()))
+            .collect();
+        for (alias, id) in self.anchors.iter() {
+            let safe = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+            if id.is_empty() || !id.chars().all(safe) {
+                return Err(Error::from(format!(
+                    "Invalid anchor \"{}\" for the tag \"{}\": anchors can only contain \
+                     letters, numbers, - and _",
+                    id, alias
+                )));
+            }
+            // our back to top links already use this id on our tags page
+            if tagger.back_to_top && tagger.anchor_prefix.is_empty() && id == TOP_ANCHOR {
+                return Err(Error::from(format!(
+                    "Invalid anchor \"{}\" for the tag \"{}\": it's reserved for our back to top \
+                     links",
+                    id, alias
+                )));
+            }
+            let alias = tagger.normalize_alias(alias);
+            let taken = tagger
+                .custom_anchors
+                .iter()
+                .find(|(other, other_id)| *other_id == id && **other != alias);
+            if let Some((other, _)) = taken {
+                return Err(Error::from(format!(
+                    "The tags \"{}\" and \"{}\" both use the anchor \"{}\"",
+                    other, alias, id
+                )));
+            }
+            tagger.custom_anchors.insert(alias, id.clone());
+        }
+        tagger.primary = self
+            .primary
+            .iter()
+            .map(|(alias, path)| (tagger.normalize_alias(alias), path.clone()))
+            .collect();
+        tagger.order = self
+            .order
+            .iter()
+            .map(|alias| tagger.normalize_alias(alias))
+            .collect();
+        tagger.featured = self
+            .featured
+            .iter()
+            .map(|alias| tagger.normalize_alias(alias))
+            .collect();
+        tagger.ignore = self
+            .ignore
+            .iter()
+            .map(|alias| tagger.normalize_alias(alias))
+            .collect();
+
+        Ok(tagger)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mdbook::book::Chapter;
+
+    #[test]
+    fn typed_options() {
+        let tagger = TaggerBuilder::new()
+            .filename("topics.md")
+            .display(Display::Name)
+            .synonym("JS", "javascript")
+            .ignore(vec!["Internal"])
+            .build()
+            .unwrap();
+
+        let mut chapter = Chapter::new(
+            "Chapter",
+            "`tag:js` `tag:internal`".into(),
+            PathBuf::from("chapter.md"),
+            vec![],
+        );
+        tagger.process_chapter(&mut chapter).unwrap();
+
+        assert_eq!(
+            r#"<a id="tag-js-1"></a>[`js`](topics.md#javascript "Tag: js") `internal`"#,
+            chapter.content
+        );
+    }
+
+    #[test]
+    fn synonyms_follow_case_sensitivity() {
+        let tagger = TaggerBuilder::new()
+            .synonym("JS", "JavaScript")
+            .case_sensitive(true)
+            .build()
+            .unwrap();
+
+        assert_eq!("JavaScript", tagger.normalize_alias("JS"));
+        assert_eq!("js", tagger.normalize_alias("js"));
+    }
+
+    #[test]
+    fn invalid_anchors() {
+        let err = TaggerBuilder::new()
+            .anchor("rust", "rust lang")
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(
+            "Invalid anchor \"rust lang\" for the tag \"rust\": anchors can only contain \
+             letters, numbers, - and _",
+            err.to_string()
+        );
+
+        let err = TaggerBuilder::new()
+            .anchor("rust", "lang")
+            .anchor("go", "lang")
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(
+            "The tags \"rust\" and \"go\" both use the anchor \"lang\"",
+            err.to_string()
+        );
+
+        let err = TaggerBuilder::new()
+            .anchor("rust", "top")
+            .back_to_top(true)
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(
+            "Invalid anchor \"top\" for the tag \"rust\": it's reserved for our back to top links",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn featured_and_split() {
+        let err = TaggerBuilder::new()
+            .featured(vec!["rust"])
+            .split(Split::AlphaRanges)
+            .build()
+            .err()
+            .unwrap();
+
+        assert_eq!(
+            "featured and split can't be used together, pick one way to break up the tags page",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn sitemap_without_base_url() {
+        let err = TaggerBuilder::new()
+            .sitemap_output("tags-sitemap.xml")
+            .build()
+            .err()
+            .unwrap();
+
+        assert_eq!(
+            "sitemap_output needs a base_url to build the absolute urls of our sitemap",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn empty_prefix() {
+        let err = TaggerBuilder::new().prefix("").build().err().unwrap();
+
+        assert_eq!("Tag prefixes can't be empty", err.to_string());
+    }
+}
@@ -0,0 +1,58 @@
+use mdbook::utils::{id_from_content, render_markdown};
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+
+/// Every id that will exist once mdbook renders our markdown as HTML. This mirrors how
+/// mdbook's HTML renderer assigns ids to headers, along with any ids in our raw HTML.
+pub fn rendered_ids(markdown: &str) -> HashSet<String> {
+    let html = render_markdown(markdown, false);
+
+    let header_regex = Regex::new(r"<h\d>(.*?)</h\d>").expect("Valid regex");
+    let id_regex = Regex::new(r#"\bid="([^"]*)""#).expect("Valid regex");
+
+    let mut ids = HashSet::new();
+
+    // duplicate header ids get a `-1`, `-2`, etc. suffix
+    let mut id_counter = HashMap::new();
+    for caps in header_regex.captures_iter(&html) {
+        let raw_id = id_from_content(&caps[1]);
+
+        let id_count = id_counter.entry(raw_id.clone()).or_insert(0);
+        let id = match *id_count {
+            0 => raw_id,
+            other => format!("{}-{}", raw_id, other),
+        };
+        *id_count += 1;
+
+        ids.insert(id);
+    }
+
+    for caps in id_regex.captures_iter(&html) {
+        ids.insert(caps[1].to_string());
+    }
+
+    ids
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ids() {
+        let ids = rendered_ids(
+            r#"# Tags
+
+<a id="hello"></a>
+
+## `hello`
+
+## `hello`
+"#,
+        );
+
+        let mut ids = ids.into_iter().collect::<Vec<_>>();
+        ids.sort();
+        assert_eq!(vec!["hello", "hello-1", "tags"], ids);
+    }
+}
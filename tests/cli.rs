@@ -0,0 +1,79 @@
+use mdbook::book::{Book, Chapter};
+use mdbook::{BookItem, Config, MDBOOK_VERSION};
+use serde_json::json;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+fn command() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_mdbook-tag"))
+}
+
+#[test]
+fn supports() {
+    let status = command().args(["supports", "html"]).status().unwrap();
+
+    assert!(status.success());
+}
+
+#[test]
+fn preprocess() {
+    let mut book = Book::new();
+    book.push_item(Chapter::new(
+        "Chapter",
+        "`tag:hello`".into(),
+        PathBuf::from("chapter.md"),
+        vec![],
+    ));
+
+    // mdbook sends us a `[context, book]` array on stdin
+    let input = json!([
+        {
+            "root": ".",
+            "config": Config::default(),
+            "renderer": "html",
+            "mdbook_version": MDBOOK_VERSION,
+        },
+        book,
+    ]);
+
+    let mut child = command()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.to_string().as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(output.status.success());
+
+    let book: Book = serde_json::from_slice(&output.stdout).unwrap();
+    let names = book
+        .iter()
+        .filter_map(|item| match item {
+            BookItem::Chapter(chapter) => Some(chapter.name.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(vec!["Chapter", "Tags"], names);
+}
+
+#[test]
+fn invalid_input() {
+    let mut child = command()
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"{}").unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains(MDBOOK_VERSION));
+}